@@ -1,13 +1,30 @@
 use crate::{
     Context,
+    embeds,
+    permissions::{ self, Category, GuildPermissions, },
+    queue::Entry,
+    sessions::{ Owner, Session, },
 };
 
+use std::{
+    sync::Arc,
+    time::{ Duration, Instant, },
+};
 use poise::{
     command,
+    serenity_prelude as serenity,
+    AutocompleteChoice,
     //builtins::autocomplete_command,   
 };
-use fishify_lib::spotify::Fishify;
-use anyhow::Result;
+use fishify_lib::{
+    backend::SpotifyBackend,
+    device::candidates,
+    spotify::Fishify,
+    response::{ Action, Item, PlaybackStatus, Response, },
+    error::FishifyError,
+    model::SkipReason,
+};
+use anyhow::{ anyhow, Error, Result, };
 use rspotify::{
     model::{
         enums::{
@@ -17,14 +34,265 @@ use rspotify::{
     },
 };
 
-fn format_response(spotify: &Fishify) -> String {
-    if spotify.show {
-        spotify.response.iter().fold(String::new(), |r, s| format!("{r}> {s}\n"))
+// how many recent queries to offer in autocomplete
+const RECENT_QUERIES: usize = 25;
+// autocomplete searches once this much is typed, and keeps results for a while, since discord
+// asks again for every key pressed
+const AUTOCOMPLETE_MIN_CHARS: usize = 3;
+const AUTOCOMPLETE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const AUTOCOMPLETE_CACHE_SIZE: usize = 500;
+// how many search results to pick from, and how long to wait for a pick
+const PICK_RESULTS: u32 = 10;
+const PICK_TIMEOUT: Duration = Duration::from_secs(60);
+// how long queue_list's page buttons keep working after the last press
+const QUEUE_BUTTONS_TIMEOUT: Duration = Duration::from_secs(120);
+// how often the player refreshes, when it stops, and how much its volume buttons change volume by
+const PLAYER_POLL: Duration = Duration::from_secs(10);
+const PLAYER_IDLE: Duration = Duration::from_secs(30 * 60);
+const PLAYER_VOLUME_STEP: i32 = 10;
+// how recently someone has to have used the bot to count as listening, when voting outside voice
+const LISTENER_WINDOW: Duration = Duration::from_secs(30 * 60);
+const DEFAULT_VOTE_FRACTION: f64 = 0.5;
+
+fn format_response(response: impl Into<Response>) -> String {
+    let response = response.into();
+    if response.show() {
+        response.to_string().lines().fold(String::new(), |r, s| format!("{r}> {s}\n"))
     } else {
-        spotify.response.join("\n")
+        response.to_string()
     }
 }
 
+// the spotify account whoever ran the command plays on: their own, their guild's, or the bot's
+fn session(ctx: Context<'_>) -> Arc<Session> {
+    ctx.data().sessions.get(ctx.guild_id(), ctx.author().id)
+}
+
+fn fishify<'a>(ctx: Context<'_>, session: &'a Session) -> Fishify<'a> {
+    Fishify::from(session.spotify.as_ref())
+        .with_max_items(ctx.data().max_context_items)
+        .with_device(ctx.data().device.clone())
+}
+
+// a fishify targeting the given device, or the bot's default one
+fn fishify_on<'a>(ctx: Context<'_>, session: &'a Session, device: Option<String>) -> Fishify<'a> {
+    fishify(ctx, session).with_device(device.or(ctx.data().device.clone()))
+}
+
+// device names from the live device list, best matches first. discord shows at most 25
+async fn autocomplete_device<'a>(ctx: Context<'_>, partial: &'a str) -> impl Iterator<Item = String> + 'a {
+    let devices = if permissions::allowed(ctx).await {
+        session(ctx).spotify.devices().await.unwrap_or_default()
+    } else {
+        vec![]
+    };
+    let names: Vec<String> = candidates(&devices, partial).into_iter()
+        .take(25)
+        .map(|device| device.name.clone())
+        .collect();
+    names.into_iter()
+}
+
+// the top tracks, albums and playlists for what's been typed so far, resolving to their uris.
+// matching recent queries until enough is typed to search for
+async fn autocomplete_query<'a>(ctx: Context<'_>, partial: &'a str) -> impl Iterator<Item = AutocompleteChoice<String>> + 'a {
+    let q = partial.trim().to_lowercase();
+    let choices: Vec<(String, String)> = if !permissions::allowed(ctx).await {
+        vec![]
+    } else if q.chars().count() < AUTOCOMPLETE_MIN_CHARS {
+        recent_queries(ctx).into_iter()
+            .filter(|query| query.to_lowercase().contains(&q))
+            .map(|query| (query.clone(), query))
+            .collect()
+    } else if let Some(cached) = cached_choices(ctx, &q) {
+        cached
+    } else {
+        let session = session(ctx);
+        let fishify = fishify(ctx, &session);
+        let (tracks, albums, playlists) = tokio::join!(
+            fishify.search(q.clone(), Some(SearchType::Track), Some(5)),
+            fishify.search(q.clone(), Some(SearchType::Album), Some(3)),
+            fishify.search(q.clone(), Some(SearchType::Playlist), Some(3)),
+        );
+        // only complete results are cached, a failed search is tried again on the next key
+        let complete = tracks.is_ok() && albums.is_ok() && playlists.is_ok();
+        let choices: Vec<(String, String)> = [tracks, albums, playlists].into_iter()
+            .filter_map(|results| results.ok())
+            .flat_map(|results| results.items)
+            .filter_map(|item| {
+                let uri = item.uri.clone()?;
+                let kind = uri.split(':').nth(1).unwrap_or_default().to_string();
+                // discord allows at most 100 characters
+                let name: String = format!("{item} ({kind})").chars().take(100).collect();
+                Some((name, uri))
+            })
+            .collect();
+        if complete {
+            cache_choices(ctx, q, choices.clone());
+        }
+        choices
+    };
+    choices.into_iter().map(|(name, value)| AutocompleteChoice { name, value })
+}
+
+fn cached_choices(ctx: Context<'_>, q: &str) -> Option<Vec<(String, String)>> {
+    let cache = ctx.data().query_cache.lock().unwrap();
+    cache.get(q)
+        .filter(|(searched, _)| searched.elapsed() < AUTOCOMPLETE_CACHE_TTL)
+        .map(|(_, choices)| choices.clone())
+}
+
+fn cache_choices(ctx: Context<'_>, q: String, choices: Vec<(String, String)>) {
+    let mut cache = ctx.data().query_cache.lock().unwrap();
+    cache.retain(|_, (searched, _)| searched.elapsed() < AUTOCOMPLETE_CACHE_TTL);
+    if cache.len() >= AUTOCOMPLETE_CACHE_SIZE {
+        let oldest = cache.iter().min_by_key(|(_, (searched, _))| *searched).map(|(q, _)| q.clone());
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(q, (Instant::now(), choices));
+}
+
+// queries searched for recently, most recent first
+async fn autocomplete_recent<'a>(ctx: Context<'_>, partial: &'a str) -> impl Iterator<Item = String> + 'a {
+    let partial = partial.to_lowercase();
+    recent_queries(ctx).into_iter().filter(move |query| query.to_lowercase().contains(&partial))
+}
+
+// recent queries and listeners are kept per guild, or per user in dms, so strangers aren't mixed in
+pub fn guild_or_user(ctx: Context<'_>) -> Owner {
+    ctx.guild_id().map(Owner::Guild).unwrap_or(Owner::User(ctx.author().id))
+}
+
+fn recent_queries(ctx: Context<'_>) -> Vec<String> {
+    ctx.data().recent_queries.lock().unwrap().get(&guild_or_user(ctx)).cloned().unwrap_or_default()
+}
+
+fn remember_query(ctx: Context<'_>, query: &str) {
+    let mut recent_queries = ctx.data().recent_queries.lock().unwrap();
+    let recent = recent_queries.entry(guild_or_user(ctx)).or_default();
+    recent.retain(|x| x != query);
+    recent.insert(0, query.to_string());
+    recent.truncate(RECENT_QUERIES);
+}
+
+// plays or queues the uri autocomplete resolved to, the search result someone picked, or the
+// first search result for what was typed
+async fn play_or_queue(ctx: Context<'_>, session: &Session, fishify: &Fishify<'_>, query: Option<String>, _type: Option<SearchTypeChoice>, is_url: bool, pick: bool, queue: bool) -> Result<()> {
+    let is_uri = query.as_deref().is_some_and(|q| q.starts_with("spotify:"));
+    if let Some(q) = query.as_deref().filter(|_| !is_url && !is_uri) {
+        remember_query(ctx, q);
+    }
+
+    match query {
+        Some(query) if pick && !is_url && !is_uri => pick_and_play(ctx, session, fishify, query, _type, queue).await,
+        // queueing goes through the bot's queue, so users take turns and it's clear who added what
+        Some(query) if queue => {
+            let item = fishify.lookup(query, _type.map(|x| x.into()), is_url).await?;
+            let (entries, position) = enqueue(ctx, session, fishify, &item).await?;
+            ctx.send(|m| m.embed(|e| embeds::queued(e, &item, entries.len(), position))).await?;
+            Ok(())
+        },
+        Some(uri) if is_uri => say_action(ctx, fishify.play_uri(&uri, false).await?).await,
+        query => say_action(ctx, fishify.play(query, _type.map(|x| x.into()), is_url, queue).await?).await,
+    }
+}
+
+// adds to the bot's queue for the feeder to pass on to spotify, returning the entries and the first's
+// place in line. Albums, playlists and shows are added as their items, so they take turns like the rest
+async fn enqueue(ctx: Context<'_>, session: &Session, fishify: &Fishify<'_>, item: &Item) -> Result<(Vec<Entry>, usize)> {
+    let uri = item.uri.clone().ok_or(FishifyError::InvalidUri(item.name.clone()))?;
+    // every item of an album or playlist is its own request to spotify
+    let limits = &ctx.data().limits;
+    if limits.max_context_size.is_some() {
+        limits.check_context_size(fishify.context_size(&uri).await?)?;
+    }
+    let items = match fishify.context_items(&uri).await? {
+        Some(items) if items.is_empty() => return Err(FishifyError::Unplayable(SkipReason::Unavailable).into()),
+        Some(items) => items,
+        None => vec![item.clone()],
+    };
+
+    let mut queue = session.queue.lock().unwrap();
+    limits.check_queued(queue.user_entries(ctx.author().id).len(), items.len())?;
+    let entries: Vec<Entry> = items.into_iter()
+        .filter_map(|item| item.uri.clone().map(|uri| queue.push(ctx.author().id, item, uri, fishify.device().map(String::from))))
+        .collect();
+    let position = queue.position(entries[0].id).unwrap_or_default();
+    Ok((entries, position))
+}
+
+// what's playing or was queued as an embed, anything else as text
+async fn say_action(ctx: Context<'_>, action: Action) -> Result<()> {
+    match embeds::action(&action) {
+        Some(embed) => ctx.send(|m| m.embed(|e| { *e = embed; e })).await?,
+        None => ctx.say(format_response(action)).await?,
+    };
+    Ok(())
+}
+
+// sends the top search results as a select menu, then plays or queues the one picked
+async fn pick_and_play(ctx: Context<'_>, session: &Session, fishify: &Fishify<'_>, query: String, _type: Option<SearchTypeChoice>, queue: bool) -> Result<()> {
+    let results = fishify.search(query, _type.map(|x| x.into()), Some(PICK_RESULTS)).await?;
+    let items: Vec<_> = results.items.into_iter().filter(|item| item.uri.is_some()).collect();
+    if items.is_empty() {
+        return Err(FishifyError::NoSearchResults.into());
+    }
+
+    let menu_id = format!("pick-{}", ctx.id());
+    let reply = ctx.send(|m| m
+        .content("Pick a result")
+        .components(|c| c.create_action_row(|row| row.create_select_menu(|menu| menu
+            .custom_id(&menu_id)
+            .placeholder("Search results")
+            .options(|options| {
+                for item in &items {
+                    // discord allows at most 100 characters
+                    let label: String = item.to_string().chars().take(100).collect();
+                    options.create_option(|option| option.label(label).value(item.uri.as_deref().unwrap_or_default()));
+                }
+                options
+            })
+        )))
+    ).await?;
+
+    let interaction = serenity::CollectComponentInteraction::new(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .channel_id(ctx.channel_id())
+        .filter(move |interaction| interaction.data.custom_id == menu_id)
+        .timeout(PICK_TIMEOUT)
+        .await;
+    let uri = match interaction {
+        Some(interaction) => {
+            interaction.create_interaction_response(ctx.serenity_context(), |r| r
+                .kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+            ).await?;
+            interaction.data.values.first().cloned()
+        },
+        None => None,
+    };
+
+    let action = match uri {
+        Some(uri) if queue => {
+            let item = fishify.lookup(uri, None, false).await?;
+            let (entries, position) = enqueue(ctx, session, fishify, &item).await?;
+            reply.edit(ctx, |m| m.content("").embed(|e| embeds::queued(e, &item, entries.len(), position)).components(|c| c)).await?;
+            return Ok(());
+        },
+        Some(uri) => fishify.play_uri(&uri, false).await?,
+        None => {
+            reply.edit(ctx, |m| m.content("Nothing picked").components(|c| c)).await?;
+            return Ok(());
+        },
+    };
+    match embeds::action(&action) {
+        Some(embed) => reply.edit(ctx, |m| m.content("").embed(|e| { *e = embed; e }).components(|c| c)).await?,
+        None => reply.edit(ctx, |m| m.content(format_response(action)).components(|c| c)).await?,
+    };
+    Ok(())
+}
+
 // idk how to do this nicely with enums i don't own
 #[derive(Debug, poise::ChoiceParameter)]
 pub enum SearchTypeChoice {
@@ -78,18 +346,22 @@ pub async fn register(ctx: Context<'_>) -> Result<()> {
 pub async fn play(
     ctx: Context<'_>,
     #[description = "Search query, or url"]
+    #[autocomplete = "autocomplete_query"]
     query: Option<String>,
     #[description = "Search type"]
     #[rename = "type"]
     _type: Option<SearchTypeChoice>,
     #[description = "Whether to treat query as url"]
     is_url: Option<bool>,
+    #[description = "Pick from the top search results instead of playing the first"]
+    pick: Option<bool>,
+    #[description = "Device id or name to play on"]
+    #[autocomplete = "autocomplete_device"]
+    device: Option<String>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.play(query, _type.map(|x| x.into()), is_url.unwrap_or(false), false).await?;
-    ctx.say(format_response(&fishify)).await?;
-
-    Ok(())
+    let session = session(ctx);
+    let fishify = fishify_on(ctx, &session, device);
+    play_or_queue(ctx, &session, &fishify, query, _type, is_url.unwrap_or(false), pick.unwrap_or(false), false).await
 }
 
 /// Queue music
@@ -97,18 +369,22 @@ pub async fn play(
 pub async fn queue(
     ctx: Context<'_>,
     #[description = "Search query, or url"]
+    #[autocomplete = "autocomplete_query"]
     query: Option<String>,
     #[description = "Search type"]
     #[rename = "type"]
     _type: Option<SearchTypeChoice>,
     #[description = "Whether to treat query as url"]
     is_url: Option<bool>,
+    #[description = "Pick from the top search results instead of playing the first"]
+    pick: Option<bool>,
+    #[description = "Device id or name to play on"]
+    #[autocomplete = "autocomplete_device"]
+    device: Option<String>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.play(query, _type.map(|x| x.into()), is_url.unwrap_or(false), true).await?;
-    ctx.say(format_response(&fishify)).await?;
-
-    Ok(())
+    let session = session(ctx);
+    let fishify = fishify_on(ctx, &session, device);
+    play_or_queue(ctx, &session, &fishify, query, _type, is_url.unwrap_or(false), pick.unwrap_or(false), true).await
 }
 
 /// Search for music
@@ -116,6 +392,7 @@ pub async fn queue(
 pub async fn search(
     ctx: Context<'_>,
     #[description = "Search query"]
+    #[autocomplete = "autocomplete_recent"]
     query: String,
     #[description = "Search type"]
     #[rename = "type"]
@@ -123,9 +400,11 @@ pub async fn search(
     #[description = "Limit number of results"]
     limit: Option<u32>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.search(query, _type.map(|x| x.into()), limit).await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    remember_query(ctx, &query);
+    let results = fishify.search(query, _type.map(|x| x.into()), limit).await?;
+    ctx.send(|m| m.embed(|e| embeds::search(e, &results))).await?;
 
     Ok(())
 }
@@ -135,9 +414,88 @@ pub async fn search(
 pub async fn queue_list(
     ctx: Context<'_>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.queue_list().await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let listing = fishify.queue_list().await?;
+    // the bot's entries that are waiting, and the ones already passed on to spotify
+    let (pending, fed): (Vec<Entry>, Vec<Entry>) = {
+        let queue = session.queue.lock().unwrap();
+        let pending = queue.ordered().into_iter().cloned().collect();
+        let fed = queue.fed.iter().chain(queue.playing.iter()).cloned().collect();
+        (pending, fed)
+    };
+    let pages = embeds::queue_pages(&listing);
+    let mut page = 0;
+
+    let buttons_id = format!("queue-{}", ctx.id());
+    let (previous_id, next_id) = (format!("{buttons_id}-previous"), format!("{buttons_id}-next"));
+    let reply = ctx.send(|m| {
+        m.embed(|e| embeds::queue(e, &listing, page, &pending, &fed));
+        if pages > 1 {
+            m.components(|c| c.create_action_row(|row| row
+                .create_button(|b| b.custom_id(&previous_id).label("Previous"))
+                .create_button(|b| b.custom_id(&next_id).label("Next"))
+            ));
+        }
+        m
+    }).await?;
+    if pages <= 1 {
+        return Ok(());
+    }
+
+    // turn pages until nobody has pressed a button for a while
+    while let Some(interaction) = serenity::CollectComponentInteraction::new(ctx.serenity_context())
+        .channel_id(ctx.channel_id())
+        .filter({
+            let buttons_id = buttons_id.clone();
+            move |interaction| interaction.data.custom_id.starts_with(&buttons_id)
+        })
+        .timeout(QUEUE_BUTTONS_TIMEOUT)
+        .await
+    {
+        page = match interaction.data.custom_id == next_id {
+            true => (page + 1) % pages,
+            false => (page + pages - 1) % pages,
+        };
+        interaction.create_interaction_response(ctx.serenity_context(), |r| r
+            .kind(serenity::InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| d.embed(|e| embeds::queue(e, &listing, page, &pending, &fed)))
+        ).await?;
+    }
+    reply.edit(ctx, |m| m.components(|c| c)).await?;
+
+    Ok(())
+}
+
+// the user's own entries in the bot's queue
+async fn autocomplete_entry<'a>(ctx: Context<'_>, partial: &'a str) -> impl Iterator<Item = AutocompleteChoice<u64>> + 'a {
+    let entries: Vec<Entry> = if permissions::allowed(ctx).await {
+        session(ctx).queue.lock().unwrap().user_entries(ctx.author().id).into_iter().cloned().collect()
+    } else {
+        vec![]
+    };
+    let partial = partial.to_lowercase();
+    entries.into_iter()
+        .filter(move |entry| entry.item.to_string().to_lowercase().contains(&partial) || entry.id.to_string() == partial)
+        .map(|entry| AutocompleteChoice {
+            name: format!("#{} {}", entry.id, entry.item).chars().take(100).collect(),
+            value: entry.id,
+        })
+}
+
+/// Remove something you queued before it's played
+#[command(slash_command)]
+pub async fn queue_remove(
+    ctx: Context<'_>,
+    #[description = "What to remove"]
+    #[autocomplete = "autocomplete_entry"]
+    entry: u64,
+) -> Result<()> {
+    // djs can remove anyone's
+    let roles = permissions::author_roles(ctx).await;
+    let is_dj = ctx.data().permissions.read().unwrap().guild(ctx.guild_id()).dj.is_dj(&roles);
+    let removed = session(ctx).queue.lock().unwrap().remove(entry, ctx.author().id, is_dj)?;
+    ctx.say(format!("Removed {} from the queue", removed.item)).await?;
 
     Ok(())
 }
@@ -146,10 +504,14 @@ pub async fn queue_list(
 #[command(slash_command)]
 pub async fn pause(
     ctx: Context<'_>,
+    #[description = "Device id or name to play on"]
+    #[autocomplete = "autocomplete_device"]
+    device: Option<String>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.pause().await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify_on(ctx, &session, device);
+    let response = fishify.pause().await?;
+    ctx.say(format_response(response)).await?;
 
     Ok(())
 }
@@ -160,10 +522,173 @@ pub async fn skip(
     ctx: Context<'_>,
     #[description = "Number of songs to skip"]
     count: Option<u8>,
+    #[description = "Device id or name to play on"]
+    #[autocomplete = "autocomplete_device"]
+    device: Option<String>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify_on(ctx, &session, device);
+    let roles = permissions::author_roles(ctx).await;
+    let message = skip_or_vote(ctx, &session, &fishify, ctx.author().id, &roles, count.unwrap_or(1)).await?;
+    ctx.say(message).await?;
+
+    Ok(())
+}
+
+// skips right away for djs or without vote skip, otherwise votes to skip the current track,
+// skipping once enough listeners have voted
+async fn skip_or_vote(ctx: Context<'_>, session: &Session, fishify: &Fishify<'_>, user: serenity::UserId, roles: &[serenity::RoleId], count: u8) -> Result<String> {
+    let dj = ctx.data().permissions.read().unwrap().guild(ctx.guild_id()).dj;
+    if dj.vote_skip.is_none() || dj.is_dj(roles) {
+        return Ok(format_response(fishify.skip(count).await?));
+    }
+
+    let track = fishify.status().await?.item.and_then(|x| x.uri);
+    let needed = dj.votes_needed(listeners(ctx, user));
+    let votes = session.votes.lock().unwrap().add(track, user);
+    if votes < needed {
+        return Ok(format!("Voted to skip, {votes}/{needed} votes"));
+    }
+    session.votes.lock().unwrap().clear();
+    Ok(format_response(fishify.skip(1).await?))
+}
+
+// who's listening: the people in the voter's voice channel, or if they aren't in one,
+// everyone who's used the bot lately
+fn listeners(ctx: Context<'_>, voter: serenity::UserId) -> usize {
+    if let Some(guild) = ctx.guild() {
+        if let Some(channel) = guild.voice_states.get(&voter).and_then(|x| x.channel_id) {
+            return guild.voice_states.values()
+                .filter(|x| x.channel_id == Some(channel))
+                .filter(|x| !x.member.as_ref().is_some_and(|member| member.user.bot))
+                .count();
+        }
+    }
+    let active = ctx.data().active_users.lock().unwrap();
+    active.get(&guild_or_user(ctx)).map_or(0, |users| users.values().filter(|x| x.elapsed() < LISTENER_WINDOW).count())
+}
+
+/// Go back to the previous song
+#[command(slash_command)]
+pub async fn previous(
+    ctx: Context<'_>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.previous().await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Seek within the current song
+#[command(slash_command)]
+pub async fn seek(
+    ctx: Context<'_>,
+    #[description = "Position, like 1:30, +15s, -30s, or 50%"]
+    position: String,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.seek(position.parse()?).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Restart the current song
+#[command(slash_command)]
+pub async fn restart(
+    ctx: Context<'_>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.restart().await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Like the current song
+#[command(slash_command)]
+pub async fn like(
+    ctx: Context<'_>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.like().await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Unlike the current song
+#[command(slash_command)]
+pub async fn unlike(
+    ctx: Context<'_>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.skip(count.unwrap_or(1)).await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.unlike().await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// List liked songs
+#[command(slash_command)]
+pub async fn library_tracks(
+    ctx: Context<'_>,
+    #[description = "Limit number of results"]
+    limit: Option<u32>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.saved_tracks(limit).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// List saved albums
+#[command(slash_command)]
+pub async fn library_albums(
+    ctx: Context<'_>,
+    #[description = "Limit number of results"]
+    limit: Option<u32>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.saved_albums(limit).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// List saved shows
+#[command(slash_command)]
+pub async fn library_shows(
+    ctx: Context<'_>,
+    #[description = "Limit number of results"]
+    limit: Option<u32>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.saved_shows(limit).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Play liked songs
+#[command(slash_command)]
+pub async fn play_liked(
+    ctx: Context<'_>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.play_liked().await?;
+    ctx.say(format_response(response)).await?;
 
     Ok(())
 }
@@ -173,21 +698,152 @@ pub async fn skip(
 pub async fn status(
     ctx: Context<'_>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.status().await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let status = fishify.status().await?;
+    ctx.send(|m| m.embed(|e| embeds::status(e, &status))).await?;
 
     Ok(())
 }
 
+/// Post a player with buttons to control playback
+#[command(slash_command)]
+pub async fn player(
+    ctx: Context<'_>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let player_id = format!("player-{}", ctx.id());
+    let mut status = fishify.status().await.map_err(|err| err.to_string());
+
+    let reply = ctx.send(|m| m
+        .embed(|e| embeds::player(e, &status))
+        .components(|c| player_buttons(c, &player_id, &status))
+    ).await?;
+
+    // refresh every PLAYER_POLL, until nobody has pressed a button for PLAYER_IDLE
+    let mut idle = Duration::ZERO;
+    while idle < PLAYER_IDLE {
+        let interaction = serenity::CollectComponentInteraction::new(ctx.serenity_context())
+            .channel_id(ctx.channel_id())
+            .filter({
+                let player_id = player_id.clone();
+                move |interaction| interaction.data.custom_id.starts_with(&player_id)
+            })
+            .timeout(PLAYER_POLL)
+            .await;
+
+        match interaction {
+            Some(interaction) => {
+                idle = Duration::ZERO;
+                interaction.create_interaction_response(ctx.serenity_context(), |r| r
+                    .kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+                ).await?;
+                let button = interaction.data.custom_id.trim_start_matches(&player_id);
+                // buttons skip command_check, so they're checked and cooled down like the commands they stand in for
+                let (command, category) = match button {
+                    "-previous" => ("previous", Category::Playback),
+                    "-next" => ("skip", Category::Playback),
+                    "-play" if status.as_ref().is_ok_and(|x| x.is_playing) => ("pause", Category::Playback),
+                    "-play" => ("play", Category::Playback),
+                    "-shuffle" => ("set_shuffle", Category::Settings),
+                    "-repeat" => ("set_repeat", Category::Settings),
+                    _ => ("set_volume", Category::Settings),
+                };
+                let roles = interaction.member.as_ref().map(|x| x.roles.clone()).unwrap_or_default();
+                let permissions = ctx.data().permissions.read().unwrap().guild(interaction.guild_id);
+                let allowed = permissions.check(command, Some(category), interaction.user.id, &roles, interaction.channel_id)
+                    .map_err(Error::from)
+                    .and_then(|_| ctx.data().limits.use_command(interaction.user.id, command).map_err(Error::from));
+                let pressed = match (allowed, button) {
+                    (Err(err), _) => Err(err),
+                    (Ok(()), "-next") => skip_or_vote(ctx, &session, &fishify, interaction.user.id, &roles, 1).await.map(Some),
+                    (Ok(()), _) => player_press(&fishify, button, &status).await.map(|_| None),
+                };
+                // confirmations are only worth sending for votes, the player itself shows the rest
+                let message = match pressed {
+                    Ok(Some(message)) if message.starts_with("Voted") => Some(message),
+                    Ok(_) => None,
+                    Err(err) => Some(err.to_string()),
+                };
+                if let Some(message) = message {
+                    interaction.create_followup_message(ctx.serenity_context(), |f| f
+                        .content(message)
+                        .ephemeral(true)
+                    ).await?;
+                }
+            },
+            None => idle += PLAYER_POLL,
+        }
+
+        let latest = fishify.status().await.map_err(|err| err.to_string());
+        if latest != status {
+            status = latest;
+            reply.edit(ctx, |m| m
+                .embed(|e| embeds::player(e, &status))
+                .components(|c| player_buttons(c, &player_id, &status))
+            ).await?;
+        }
+    }
+    reply.edit(ctx, |m| m.components(|c| c)).await?;
+
+    Ok(())
+}
+
+fn player_buttons<'a>(c: &'a mut serenity::CreateComponents, player_id: &str, status: &Result<PlaybackStatus, String>) -> &'a mut serenity::CreateComponents {
+    let (is_playing, shuffle) = match status {
+        Ok(playback) => (playback.is_playing, playback.shuffle),
+        Err(_) => (false, false),
+    };
+    let style = |on: bool| if on { serenity::ButtonStyle::Success } else { serenity::ButtonStyle::Secondary };
+    c.create_action_row(|row| row
+        .create_button(|b| b.custom_id(format!("{player_id}-previous")).label("\u{23EE}").style(serenity::ButtonStyle::Secondary))
+        .create_button(|b| b.custom_id(format!("{player_id}-play")).label(if is_playing { "\u{23F8}" } else { "\u{25B6}" }).style(serenity::ButtonStyle::Primary))
+        .create_button(|b| b.custom_id(format!("{player_id}-next")).label("\u{23ED}").style(serenity::ButtonStyle::Secondary))
+    )
+    .create_action_row(|row| row
+        .create_button(|b| b.custom_id(format!("{player_id}-shuffle")).label("\u{1F500}").style(style(shuffle)))
+        .create_button(|b| b.custom_id(format!("{player_id}-repeat")).label("\u{1F501}").style(serenity::ButtonStyle::Secondary))
+        .create_button(|b| b.custom_id(format!("{player_id}-volume-down")).label("\u{1F509}").style(serenity::ButtonStyle::Secondary))
+        .create_button(|b| b.custom_id(format!("{player_id}-volume-up")).label("\u{1F50A}").style(serenity::ButtonStyle::Secondary))
+    )
+}
+
+// runs what a player button does, based on the status the player is showing
+async fn player_press(fishify: &Fishify<'_>, button: &str, status: &Result<PlaybackStatus, String>) -> Result<Action> {
+    let playback = status.as_ref().ok();
+    let action = match button {
+        "-previous" => fishify.previous().await,
+        "-play" if playback.is_some_and(|x| x.is_playing) => fishify.pause().await,
+        "-play" => fishify.play(None, None, false, false).await,
+        "-shuffle" => fishify.set_shuffle(!playback.is_some_and(|x| x.shuffle)).await,
+        "-repeat" => {
+            let next = match playback.map(|x| x.repeat) {
+                Some(RepeatState::Off) | None => RepeatState::Context,
+                Some(RepeatState::Context) => RepeatState::Track,
+                Some(RepeatState::Track) => RepeatState::Off,
+            };
+            fishify.set_repeat(next).await
+        },
+        "-volume-down" | "-volume-up" => {
+            let volume = playback.and_then(|x| x.volume).unwrap_or(50) as i32;
+            let step = if button == "-volume-up" { PLAYER_VOLUME_STEP } else { -PLAYER_VOLUME_STEP };
+            fishify.set_volume((volume + step).clamp(0, 100) as u8).await
+        },
+        _ => return Err(anyhow!("Unknown button {button}")),
+    };
+    Ok(action?)
+}
+
 /// List available devices
 #[command(slash_command)]
 pub async fn device_list(
     ctx: Context<'_>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.device_list().await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.device_list().await?;
+    ctx.say(format_response(response)).await?;
 
     Ok(())
 }
@@ -197,11 +853,13 @@ pub async fn device_list(
 pub async fn device_connect(
     ctx: Context<'_>,
     #[description = "Name of device"]
+    #[autocomplete = "autocomplete_device"]
     name: Option<String>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.device_connect(name).await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.device_connect(name).await?;
+    ctx.say(format_response(response)).await?;
 
     Ok(())
 }
@@ -211,9 +869,10 @@ pub async fn device_connect(
 pub async fn device_status(
     ctx: Context<'_>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.device_status().await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.device_status().await?;
+    ctx.say(format_response(response)).await?;
 
     Ok(())
 }
@@ -224,10 +883,14 @@ pub async fn set_volume(
     ctx: Context<'_>,
     #[description = "Volume level"]
     level: u8,
+    #[description = "Device id or name to play on"]
+    #[autocomplete = "autocomplete_device"]
+    device: Option<String>,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.set_volume(level).await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify_on(ctx, &session, device);
+    let response = fishify.set_volume(level).await?;
+    ctx.say(format_response(response)).await?;
 
     Ok(())
 }
@@ -239,9 +902,10 @@ pub async fn set_shuffle(
     #[description = "Shuffle state"]
     state: bool,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.set_shuffle(state).await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.set_shuffle(state).await?;
+    ctx.say(format_response(response)).await?;
 
     Ok(())
 }
@@ -253,10 +917,368 @@ pub async fn set_repeat(
     #[description = "Repeat state"]
     state: RepeatStateChoice,
 ) -> Result<()> {
-    let mut fishify = Fishify::from(&ctx.data().spotify);
-    fishify.set_repeat(state.into()).await?;
-    ctx.say(format_response(&fishify)).await?;
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.set_repeat(state.into()).await?;
+    ctx.say(format_response(response)).await?;
 
     Ok(())
 }
 
+
+// the playlist a channel builds together, used when a playlist command is given no playlist.
+// named with the channel's id too, since every guild has a #general
+async fn channel_playlist(ctx: Context<'_>, fishify: &Fishify<'_>) -> Result<String> {
+    let channel = ctx.channel_id();
+    let channel_name = channel.name(ctx.serenity_context()).await.unwrap_or(channel.to_string());
+    let name = format!("fishify #{channel_name} ({channel})");
+
+    match fishify.playlist_show(&name, Some(1)).await {
+        Err(FishifyError::PlaylistNotFound(_)) => {
+            fishify.playlist_create(name.clone(), false).await?;
+        },
+        Err(err) => return Err(err.into()),
+        Ok(_) => (),
+    }
+
+    Ok(name)
+}
+
+/// Create a playlist
+#[command(slash_command)]
+pub async fn playlist_create(
+    ctx: Context<'_>,
+    #[description = "Name of playlist"]
+    name: String,
+    #[description = "Whether the playlist is public"]
+    public: Option<bool>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.playlist_create(name, public.unwrap_or(false)).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Add music to a playlist, the current track if query is empty
+#[command(slash_command)]
+pub async fn playlist_add(
+    ctx: Context<'_>,
+    #[description = "Search query, or url"]
+    query: Option<String>,
+    #[description = "Search type"]
+    #[rename = "type"]
+    _type: Option<SearchTypeChoice>,
+    #[description = "Whether to treat query as url"]
+    is_url: Option<bool>,
+    #[description = "Playlist name, uri, or url, defaults to the channel playlist"]
+    playlist: Option<String>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let playlist = match playlist {
+        Some(playlist) => playlist,
+        None => channel_playlist(ctx, &fishify).await?,
+    };
+    let response = fishify.playlist_add(&playlist, query, _type.map(|x| x.into()), is_url.unwrap_or(false)).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Remove the item at a position from a playlist
+#[command(slash_command)]
+pub async fn playlist_remove(
+    ctx: Context<'_>,
+    #[description = "Position of the item, as listed by playlist_show"]
+    position: u32,
+    #[description = "Playlist name, uri, or url, defaults to the channel playlist"]
+    playlist: Option<String>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let playlist = match playlist {
+        Some(playlist) => playlist,
+        None => channel_playlist(ctx, &fishify).await?,
+    };
+    let response = fishify.playlist_remove(&playlist, position).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Move an item in a playlist to another position
+#[command(slash_command)]
+pub async fn playlist_move(
+    ctx: Context<'_>,
+    #[description = "Current position of the item"]
+    from: u32,
+    #[description = "New position of the item"]
+    to: u32,
+    #[description = "Playlist name, uri, or url, defaults to the channel playlist"]
+    playlist: Option<String>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let playlist = match playlist {
+        Some(playlist) => playlist,
+        None => channel_playlist(ctx, &fishify).await?,
+    };
+    let response = fishify.playlist_move(&playlist, from, to).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Rename a playlist
+#[command(slash_command)]
+pub async fn playlist_rename(
+    ctx: Context<'_>,
+    #[description = "Playlist name, uri, or url"]
+    playlist: String,
+    #[description = "New name"]
+    name: String,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.playlist_rename(&playlist, name).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// List playlists
+#[command(slash_command)]
+pub async fn playlist_list(
+    ctx: Context<'_>,
+    #[description = "Limit number of results"]
+    limit: Option<u32>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let response = fishify.playlists(limit).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+/// Show the contents of a playlist
+#[command(slash_command)]
+pub async fn playlist_show(
+    ctx: Context<'_>,
+    #[description = "Playlist name, uri, or url, defaults to the channel playlist"]
+    playlist: Option<String>,
+    #[description = "Limit number of results"]
+    limit: Option<u32>,
+) -> Result<()> {
+    let session = session(ctx);
+    let fishify = fishify(ctx, &session);
+    let playlist = match playlist {
+        Some(playlist) => playlist,
+        None => channel_playlist(ctx, &fishify).await?,
+    };
+    let response = fishify.playlist_show(&playlist, limit).await?;
+    ctx.say(format_response(response)).await?;
+
+    Ok(())
+}
+
+// changes this guild's permissions and saves them, if there's somewhere to save them
+fn update_permissions(ctx: Context<'_>, update: impl FnOnce(&mut GuildPermissions)) -> Result<String> {
+    let mut permissions = ctx.data().permissions.write().unwrap();
+    let guild = permissions.guild_mut(ctx.guild_id().unwrap());
+    update(guild);
+    let summary = guild.to_string();
+    if let Some(path) = &ctx.data().permissions_path {
+        permissions.save(path)?;
+    }
+    Ok(summary)
+}
+
+/// Allow or disallow a role to use a category of commands
+#[command(slash_command, guild_only = true, owners_only = true)]
+pub async fn permissions_role(
+    ctx: Context<'_>,
+    #[description = "Category of commands"]
+    category: Category,
+    #[description = "Role"]
+    role: serenity::Role,
+    #[description = "Whether to allow the role, true by default"]
+    allow: Option<bool>,
+) -> Result<()> {
+    let summary = update_permissions(ctx, |permissions| {
+        let roles = &mut permissions.rules.entry(category).or_default().roles;
+        match allow.unwrap_or(true) {
+            true => roles.insert(role.id.0),
+            false => roles.remove(&role.id.0),
+        };
+    })?;
+    ctx.send(|m| m.content(summary).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Allow or disallow a category of commands in a channel
+#[command(slash_command, guild_only = true, owners_only = true)]
+pub async fn permissions_channel(
+    ctx: Context<'_>,
+    #[description = "Category of commands"]
+    category: Category,
+    #[description = "Channel"]
+    channel: serenity::Channel,
+    #[description = "Whether to allow the channel, true by default"]
+    allow: Option<bool>,
+) -> Result<()> {
+    let summary = update_permissions(ctx, |permissions| {
+        let channels = &mut permissions.rules.entry(category).or_default().channels;
+        match allow.unwrap_or(true) {
+            true => channels.insert(channel.id().0),
+            false => channels.remove(&channel.id().0),
+        };
+    })?;
+    ctx.send(|m| m.content(summary).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Ban or unban a user from using the bot
+#[command(slash_command, guild_only = true, owners_only = true)]
+pub async fn permissions_ban(
+    ctx: Context<'_>,
+    #[description = "User"]
+    user: serenity::User,
+    #[description = "Whether to ban the user, true by default"]
+    banned: Option<bool>,
+) -> Result<()> {
+    let summary = update_permissions(ctx, |permissions| {
+        match banned.unwrap_or(true) {
+            true => permissions.banned.insert(user.id.0),
+            false => permissions.banned.remove(&user.id.0),
+        };
+    })?;
+    ctx.send(|m| m.content(summary).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Show who can use which commands
+#[command(slash_command, guild_only = true, owners_only = true)]
+pub async fn permissions_show(
+    ctx: Context<'_>,
+) -> Result<()> {
+    let summary = ctx.data().permissions.read().unwrap().guild(ctx.guild_id()).to_string();
+    ctx.send(|m| m.content(summary).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Allow or disallow a role to skip without voting, and to play and change settings in dj mode
+#[command(slash_command, guild_only = true, owners_only = true)]
+pub async fn dj_role(
+    ctx: Context<'_>,
+    #[description = "Role"]
+    role: serenity::Role,
+    #[description = "Whether the role is a dj role, true by default"]
+    allow: Option<bool>,
+) -> Result<()> {
+    let summary = update_permissions(ctx, |permissions| {
+        match allow.unwrap_or(true) {
+            true => permissions.dj.roles.insert(role.id.0),
+            false => permissions.dj.roles.remove(&role.id.0),
+        };
+    })?;
+    ctx.send(|m| m.content(summary).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Only let djs play and change settings, everyone can still queue
+#[command(slash_command, guild_only = true, owners_only = true)]
+pub async fn dj_mode(
+    ctx: Context<'_>,
+    #[description = "Whether dj mode is on"]
+    enabled: bool,
+) -> Result<()> {
+    let summary = update_permissions(ctx, |permissions| permissions.dj.dj_only = enabled)?;
+    ctx.send(|m| m.content(summary).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Make skip a vote among listeners
+#[command(slash_command, guild_only = true, owners_only = true)]
+pub async fn vote_skip(
+    ctx: Context<'_>,
+    #[description = "Whether skipping needs votes"]
+    enabled: bool,
+    #[description = "Fraction of listeners that has to vote, 0.5 by default"]
+    #[min = 0.0]
+    #[max = 1.0]
+    fraction: Option<f64>,
+) -> Result<()> {
+    let summary = update_permissions(ctx, |permissions| {
+        permissions.dj.vote_skip = enabled.then(|| fraction.unwrap_or(DEFAULT_VOTE_FRACTION));
+    })?;
+    session(ctx).votes.lock().unwrap().clear();
+    ctx.send(|m| m.content(summary).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Play on your own spotify account in this server instead of the bot's
+#[command(slash_command, guild_only = true, required_permissions = "MANAGE_GUILD")]
+pub async fn link(
+    ctx: Context<'_>,
+) -> Result<()> {
+    send_link(ctx, Owner::Guild(ctx.guild_id().unwrap())).await
+}
+
+/// Play on your own spotify account wherever you use the bot
+#[command(slash_command)]
+pub async fn link_me(
+    ctx: Context<'_>,
+) -> Result<()> {
+    send_link(ctx, Owner::User(ctx.author().id)).await
+}
+
+// the authorize url goes by dm, since anyone who opens it can link their account in the owner's place
+async fn send_link(ctx: Context<'_>, owner: Owner) -> Result<()> {
+    let url = ctx.data().sessions.link_url(owner)?;
+    ctx.author().direct_message(ctx.serenity_context(), |m| m
+        .content(format!("Open this to let the bot play on your spotify account for {owner}, it works for 10 minutes:\n{url}"))
+    ).await?;
+    ctx.send(|m| m.content("Check your dms for a link to spotify").ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Go back to the bot's spotify account in this server
+#[command(slash_command, guild_only = true, required_permissions = "MANAGE_GUILD")]
+pub async fn unlink(
+    ctx: Context<'_>,
+) -> Result<()> {
+    let unlinked = ctx.data().sessions.unlink(Owner::Guild(ctx.guild_id().unwrap()))?;
+    let message = match unlinked {
+        true => "This server is back on the bot's spotify account",
+        false => "This server wasn't linked to a spotify account",
+    };
+    ctx.send(|m| m.content(message).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Stop playing on your own spotify account
+#[command(slash_command)]
+pub async fn unlink_me(
+    ctx: Context<'_>,
+) -> Result<()> {
+    let unlinked = ctx.data().sessions.unlink(Owner::User(ctx.author().id))?;
+    let message = match unlinked {
+        true => "You're back on this server's spotify account",
+        false => "You weren't linked to a spotify account",
+    };
+    ctx.send(|m| m.content(message).ephemeral(true)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+use serde::{ Deserialize, Serialize, };
+use poise::serenity_prelude::{ RoleId, UserId, };
+
+// what dj mode keeps from everyone who isn't a dj, queueing stays open to everyone
+pub const DJ_ONLY_COMMANDS: [&str; 5] = ["play", "play_liked", "set_volume", "set_shuffle", "set_repeat"];
+
+// Saved with the permissions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dj {
+    pub roles: HashSet<u64>,
+    pub dj_only: bool,
+    // the fraction of listeners that has to vote before a skip happens, skips are immediate if unset.
+    // djs always skip immediately
+    pub vote_skip: Option<f64>,
+}
+
+impl Dj {
+    pub fn is_dj(&self, roles: &[RoleId]) -> bool {
+        roles.iter().any(|role| self.roles.contains(&role.0))
+    }
+
+    pub fn allows(&self, command: &str, roles: &[RoleId]) -> bool {
+        !self.dj_only || !DJ_ONLY_COMMANDS.contains(&command) || self.is_dj(roles)
+    }
+
+    // always at least one vote, so a lone listener can still skip
+    pub fn votes_needed(&self, listeners: usize) -> usize {
+        let fraction = self.vote_skip.unwrap_or(0.0);
+        ((fraction * listeners as f64).ceil() as usize).max(1)
+    }
+}
+
+// Votes to skip the track that was playing when voting started
+#[derive(Debug, Clone, Default)]
+pub struct Vote {
+    track: Option<String>,
+    voters: HashSet<UserId>,
+}
+
+impl Vote {
+    // adds a vote, forgetting the votes for any earlier track. Returns how many votes there are
+    pub fn add(&mut self, track: Option<String>, voter: UserId) -> usize {
+        if self.track != track {
+            self.track = track;
+            self.voters.clear();
+        }
+        self.voters.insert(voter);
+        self.voters.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.track = None;
+        self.voters.clear();
+    }
+}
@@ -0,0 +1,183 @@
+use crate::queue::Entry;
+
+use fishify_lib::{
+    clock::duration_clock_format,
+    response::{ Action, Item, PlaybackStatus, QueueListing, SearchResults, },
+};
+use poise::serenity_prelude::CreateEmbed;
+
+// spotify green
+const COLOR: u32 = 0x1DB954;
+const PROGRESS_BAR_WIDTH: usize = 20;
+pub const QUEUE_PAGE_SIZE: usize = 10;
+// discord's limits on an embed field's value and on its description
+const FIELD_LIMIT: usize = 1024;
+const DESCRIPTION_LIMIT: usize = 4096;
+
+// "[name](url)", or just the name if there's no link
+fn link(name: &str, url: Option<&str>) -> String {
+    match url {
+        Some(url) => format!("[{name}]({url})"),
+        None => name.to_string(),
+    }
+}
+
+fn item_line(item: &Item) -> String {
+    let name = link(&item.name, item.url.as_deref());
+    match &item.artist {
+        Some(artist) => format!("{name} \u{2014} {}", link(artist, item.artist_url.as_deref())),
+        None => name,
+    }
+}
+
+// ▬▬▬▬🔘────────
+fn progress_bar(progress_ms: i64, duration_ms: i64) -> String {
+    let filled = match duration_ms {
+        0 => 0,
+        total => (progress_ms * PROGRESS_BAR_WIDTH as i64 / total).clamp(0, PROGRESS_BAR_WIDTH as i64 - 1) as usize,
+    };
+    format!("{}\u{1F518}{}", "\u{25AC}".repeat(filled), "\u{2500}".repeat(PROGRESS_BAR_WIDTH - 1 - filled))
+}
+
+// the item as the embed's title, artist and thumbnail
+pub fn item<'a>(embed: &'a mut CreateEmbed, item: &Item) -> &'a mut CreateEmbed {
+    embed.color(COLOR).title(&item.name);
+    if let Some(url) = &item.url {
+        embed.url(url);
+    }
+    if let Some(artist) = &item.artist {
+        embed.description(link(artist, item.artist_url.as_deref()));
+    }
+    if let Some(image) = &item.image {
+        embed.thumbnail(image);
+    }
+    embed
+}
+
+// None for actions that are only a confirmation, which are sent as text
+pub fn action(action: &Action) -> Option<CreateEmbed> {
+    let mut embed = CreateEmbed::default();
+    match action {
+        Action::Playing(playing) => item(&mut embed, playing).author(|a| a.name("Now playing")),
+        Action::Queued(queued) => item(&mut embed, queued).author(|a| a.name("Queued")),
+        Action::QueuedContext { item: context, queued, skipped } => {
+            item(&mut embed, context).author(|a| a.name(format!("Queued {queued} items")));
+            match skipped.count() {
+                0 => &mut embed,
+                1 => embed.footer(|f| f.text(format!("{skipped} was not queued"))),
+                _ => embed.footer(|f| f.text(format!("{skipped} were not queued"))),
+            }
+        },
+        _ => return None,
+    };
+    Some(embed)
+}
+
+pub fn status<'a>(embed: &'a mut CreateEmbed, status: &PlaybackStatus) -> &'a mut CreateEmbed {
+    match &status.item {
+        Some(playing) => item(embed, playing),
+        None => embed.color(COLOR).title("Nothing playing"),
+    };
+    embed.author(|a| a.name(if status.is_playing { "Playing" } else { "Paused" }));
+
+    if let Some(context) = &status.context {
+        embed.field(format!("{:?}", context._type), &context.name, false);
+    }
+    if let (Some(progress), Some(duration)) = (status.progress, status.item.as_ref().and_then(|x| x.duration)) {
+        let bar = progress_bar(progress.num_milliseconds(), duration.num_milliseconds());
+        embed.field("Progress", format!("{bar} {} / {}", duration_clock_format(progress), duration_clock_format(duration)), false);
+    }
+    if let Some(volume) = status.volume {
+        embed.field("Volume", format!("{volume}%"), true);
+    }
+    embed
+        .field("Shuffle", if status.shuffle { "On" } else { "Off" }, true)
+        .field("Repeat", format!("{:?}", status.repeat), true)
+}
+
+// the status, or why there isn't one
+pub fn player<'a>(embed: &'a mut CreateEmbed, status: &Result<PlaybackStatus, String>) -> &'a mut CreateEmbed {
+    match status {
+        Ok(playback) => self::status(embed, playback),
+        Err(err) => embed.color(COLOR).title("Nothing playing").description(err),
+    }
+}
+
+// something added to the bot's queue, as one entry or one per item of an album, playlist or show, and
+// where the first is in line
+pub fn queued<'a>(embed: &'a mut CreateEmbed, queued: &Item, entries: usize, position: usize) -> &'a mut CreateEmbed {
+    let author = match entries {
+        1 => format!("Queued, #{position} in line"),
+        _ => format!("Queued {entries} items, the first #{position} in line"),
+    };
+    item(embed, queued).author(|a| a.name(author))
+}
+
+// lines, as many as fit in a field or description of at most limit characters
+fn fit_lines(lines: Vec<String>, limit: usize) -> String {
+    let mut value = String::new();
+    for line in lines {
+        if value.len() + line.len() + 1 > limit {
+            break;
+        }
+        value.push_str(&line);
+        value.push('\n');
+    }
+    value
+}
+
+pub fn queue_pages(queue: &QueueListing) -> usize {
+    queue.queue.len().div_ceil(QUEUE_PAGE_SIZE).max(1)
+}
+
+// page starts at 0. pending is what's waiting in the bot's queue, and fed what it already passed
+// on to spotify, to show who added them
+pub fn queue<'a>(embed: &'a mut CreateEmbed, queue: &QueueListing, page: usize, pending: &[Entry], fed: &[Entry]) -> &'a mut CreateEmbed {
+    let added_by = |item: &Item| {
+        let added = fed.iter().find(|entry| item.uri.as_deref() == Some(entry.uri.as_str()));
+        match added {
+            Some(entry) => format!("{}, added by <@{}>", item_line(item), entry.user),
+            None => item_line(item),
+        }
+    };
+
+    embed.color(COLOR).title("Queue");
+    if let Some(playing) = &queue.currently_playing {
+        embed.field("Now playing", added_by(playing), false);
+        if let Some(image) = &playing.image {
+            embed.thumbnail(image);
+        }
+    }
+
+    let start = page * QUEUE_PAGE_SIZE;
+    let lines: Vec<String> = queue.queue.iter().enumerate()
+        .skip(start)
+        .take(QUEUE_PAGE_SIZE)
+        .map(|(i, x)| format!("{}. {}", i + 1, added_by(x)))
+        .collect();
+    match lines.is_empty() {
+        true => embed.description("The queue is empty"),
+        false => embed.description(lines.join("\n")),
+    };
+    if !pending.is_empty() {
+        let lines = pending.iter()
+            .map(|entry| format!("`#{}` {}, by <@{}> {}", entry.id, entry.item, entry.user, entry.added_ago()))
+            .collect();
+        embed.field("Waiting their turn", fit_lines(lines, FIELD_LIMIT), false);
+    }
+    embed.footer(|f| f.text(format!("Page {}/{}", page + 1, queue_pages(queue))))
+}
+
+pub fn search<'a>(embed: &'a mut CreateEmbed, results: &SearchResults) -> &'a mut CreateEmbed {
+    embed.color(COLOR).title("Search results");
+    if let Some(image) = results.items.first().and_then(|x| x.image.as_ref()) {
+        embed.thumbnail(image);
+    }
+    let lines: Vec<String> = results.items.iter().enumerate()
+        .map(|(i, x)| format!("{}. {}", i + 1, item_line(x)))
+        .collect();
+    match lines.is_empty() {
+        true => embed.description("No results"),
+        false => embed.description(fit_lines(lines, DESCRIPTION_LIMIT)),
+    }
+}
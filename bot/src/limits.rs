@@ -0,0 +1,103 @@
+use crate::Context;
+
+use std::{
+    collections::HashMap,
+    env::var,
+    fmt,
+    sync::Mutex,
+    time::{ Duration, Instant, },
+};
+use anyhow::Result;
+use poise::serenity_prelude::UserId;
+
+// How much each user can do, from env vars. Anything unset is unlimited
+#[derive(Debug, Default)]
+pub struct Limits {
+    // entries waiting in the bot's queue, per user
+    pub max_queued: Option<usize>,
+    // the most items an album, playlist or show can have to be queued
+    pub max_context_size: Option<u32>,
+    // how long a user waits between uses of a command
+    pub cooldowns: HashMap<String, Duration>,
+    // when each user last used each command with a cooldown
+    used: Mutex<HashMap<(UserId, String), Instant>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    Cooldown { command: String, wait: u64 },
+    TooManyQueued(usize),
+    ContextTooLarge { size: u32, max: u32 },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cooldown { command, wait } => write!(f, "You can use /{command} again in {wait} seconds"),
+            Self::TooManyQueued(max) => write!(f, "You can have at most {max} items waiting in the queue, wait for yours to play first"),
+            Self::ContextTooLarge { size, max } => write!(f, "That has {size} items, at most {max} can be queued at once"),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+impl Limits {
+    // MAX_QUEUED_PER_USER, MAX_QUEUED_CONTEXT_SIZE, and COOLDOWNS like "skip=10,queue=5" in seconds
+    pub fn from_env() -> Self {
+        let cooldowns = var("COOLDOWNS").unwrap_or_default().split(',')
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| {
+                let (command, secs) = x.split_once('=').expect("`COOLDOWNS` must look like `skip=10,queue=5`");
+                let secs = secs.trim().parse().expect("`COOLDOWNS` must be in seconds");
+                (command.trim().to_string(), Duration::from_secs(secs))
+            })
+            .collect();
+
+        return Self {
+            max_queued: var("MAX_QUEUED_PER_USER").ok().map(|x| x.parse().expect("`MAX_QUEUED_PER_USER` must be a number")),
+            max_context_size: var("MAX_QUEUED_CONTEXT_SIZE").ok().map(|x| x.parse().expect("`MAX_QUEUED_CONTEXT_SIZE` must be a number")),
+            cooldowns,
+            used: Mutex::new(HashMap::new()),
+        };
+    }
+
+    // starts the cooldown, unless the user is still in it
+    pub fn use_command(&self, user: UserId, command: &str) -> Result<(), LimitError> {
+        let cooldown = match self.cooldowns.get(command) {
+            Some(cooldown) => *cooldown,
+            None => return Ok(()),
+        };
+        let mut used = self.used.lock().unwrap();
+        let key = (user, command.to_string());
+        if let Some(last) = used.get(&key) {
+            if last.elapsed() < cooldown {
+                let wait = (cooldown - last.elapsed()).as_secs() + 1;
+                return Err(LimitError::Cooldown { command: command.to_string(), wait });
+            }
+        }
+        used.insert(key, Instant::now());
+        Ok(())
+    }
+
+    // whether a user with `queued` items waiting can add `adding` more
+    pub fn check_queued(&self, queued: usize, adding: usize) -> Result<(), LimitError> {
+        match self.max_queued {
+            Some(max) if queued + adding > max => Err(LimitError::TooManyQueued(max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_context_size(&self, size: Option<u32>) -> Result<(), LimitError> {
+        match (size, self.max_context_size) {
+            (Some(size), Some(max)) if size > max => Err(LimitError::ContextTooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+}
+
+// part of poise's command_check, after permissions
+pub async fn check(ctx: Context<'_>) -> Result<bool> {
+    ctx.data().limits.use_command(ctx.author().id, &ctx.command().name)?;
+    Ok(true)
+}
@@ -1,33 +1,87 @@
 mod commands;
+mod dj;
+mod embeds;
+mod limits;
+mod permissions;
+mod queue;
+mod sessions;
 
-use std::env::var;
+use std::{
+    collections::HashMap,
+    env::var,
+    path::{ Path, PathBuf, },
+    sync::{ Arc, Mutex, RwLock, },
+    time::Instant,
+};
 use poise::serenity_prelude as serenity;
 use anyhow::{ Result, Error, };
+use limits::{ LimitError, Limits, };
+use permissions::{ categorized, Category, Permissions, };
+use sessions::{ Client, Owner, SessionOptions, Sessions, };
+use fishify_lib::{
+    auth::{ self, LoginClient, },
+    config::AuthMode,
+    error::FishifyError,
+    retry::CallLimit,
+};
 use rspotify::{ 
     Credentials, OAuth, Config, scopes,
-    AuthCodeSpotify, ClientError, 
-    clients::{ BaseClient, OAuthClient, },
-    http::HttpError,
 };
 
 type Context<'a> = poise::Context<'a, Data, Error>;
 
 pub struct Data {
-    spotify: AuthCodeSpotify,
+    // the bot's spotify account, and the ones guilds and users linked
+    sessions: Arc<Sessions>,
+    // the most items to queue from an album, playlist or show
+    max_context_items: Option<u32>,
+    // a device id or name to play on instead of whichever one is active
+    device: Option<String>,
+    // most recent first, for autocompleting searches. by guild, or by user in dms
+    recent_queries: Mutex<HashMap<Owner, Vec<String>>>,
+    // autocomplete choices by what was typed, as (name, uri), and when they were searched for
+    query_cache: Mutex<HashMap<String, (Instant, Vec<(String, String)>)>>,
+    // who can use which commands where, changed by the owner-only permissions commands
+    permissions: RwLock<Permissions>,
+    // where permissions are saved, they only last until a restart without it
+    permissions_path: Option<PathBuf>,
+    // when each user last ran a command, by guild or by user in dms, to count listeners when voting
+    // outside voice channels
+    active_users: Mutex<HashMap<Owner, HashMap<serenity::UserId, Instant>>>,
+    limits: Limits,
+}
+
+// limits are only shown to whoever hit them
+fn is_limit(error: &Error) -> bool {
+    error.downcast_ref::<LimitError>().is_some()
 }
 
 // error handler
 async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
     match error {
         poise::FrameworkError::Setup { error, .. } => panic!("Failed to start bot: {:?}", error),
+        poise::FrameworkError::CommandCheckFailed { error: Some(error), ctx } => {
+            if let Err(e) = ctx.send(|m| m.content(error.to_string()).ephemeral(true)).await {
+                println!("Error while responding with error: {}", e);
+            }
+        }
+        poise::FrameworkError::Command { error, ctx } if is_limit(&error) => {
+            if let Err(e) = ctx.send(|m| m.content(error.to_string()).ephemeral(true)).await {
+                println!("Error while responding with error: {}", e);
+            }
+        }
         poise::FrameworkError::Command { error, ctx } => {
-            if let Some(ClientError::Http(http)) = error.downcast_ref::<ClientError>() {
-                if let HttpError::StatusCode(response) = http.as_ref() {
-                    if response.status() == 404 {
-                        println!("TODO: Try device connect and retry command");
-                        // if success: return
-                    }
+            // errors fishify can explain are shown as they are, anything else is a bug report.
+            // missing devices, rate limits and expired tokens were already retried by Retry
+            if let Some(err) = error.downcast_ref::<FishifyError>().filter(|x| !matches!(x, FishifyError::Other(_))) {
+                let msg = match err {
+                    FishifyError::NoActiveDevice => format!("{err}, try `/device_connect`"),
+                    _ => err.to_string(),
+                };
+                if let Err(e) = ctx.say(msg).await {
+                    println!("Error while responding with error: {}", e);
                 }
+                return;
             }
             let errmsg = format!("Error in command `{}`: {:?}", ctx.command().name, error,);
             println!("{}", &errmsg);
@@ -43,53 +97,157 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
     }
 }
 
+// with the url spotify redirected to from SPOTIFY_REDIRECTED_URL, or else by printing a url and waiting
+// for the redirect
+async fn login(spotify: &mut impl LoginClient, callback_port: u16, pending_path: &Path) -> Result<()> {
+    match var("SPOTIFY_REDIRECTED_URL") {
+        Ok(redirected) => auth::finish_login(spotify, pending_path, &redirected).await,
+        Err(_) => {
+            let url = auth::start_login(spotify, pending_path)?;
+            println!("Open this url to authorize the bot:\n{url}");
+            auth::wait_for_login(spotify, callback_port, pending_path).await
+        },
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
 
     // spotify
-    let creds = Credentials::from_env().expect("Missing `RSPOTIFY_CLIENT_ID` or `RSPOTIFY_CLIENT_SECRET` env var.");
+    // SPOTIFY_AUTH=pkce logs in without RSPOTIFY_CLIENT_SECRET
+    let auth_mode = match var("SPOTIFY_AUTH").as_deref() {
+        Ok("pkce") => AuthMode::Pkce,
+        Ok("code") | Err(_) => AuthMode::Code,
+        Ok(other) => panic!("`SPOTIFY_AUTH` must be `code` or `pkce`, not `{other}`"),
+    };
+    let creds = Credentials::from_env().expect("Missing `RSPOTIFY_CLIENT_ID` env var.");
+    if auth_mode == AuthMode::Code && creds.secret.is_none() {
+        panic!("Missing `RSPOTIFY_CLIENT_SECRET` env var, or set `SPOTIFY_AUTH=pkce` to log in without it.");
+    }
     let oauth = OAuth::from_env(scopes!(
         "user-modify-playback-state", 
-        "user-read-playback-state"
+        "user-read-playback-state",
+        "user-library-read",
+        "user-library-modify",
+        "playlist-read-private",
+        "playlist-read-collaborative",
+        "playlist-modify-public",
+        "playlist-modify-private"
     )).expect("Missing `RSPOTIFY_REDIRECT_URI` env var.");
-    let config = Config {
+    let mut config = Config {
         token_refreshing: true,
         token_cached: true,
         cache_path: var("CACHE_PATH").expect("Missing `CACHE_PATH` env var.").into(),
         ..Default::default()
     };
-    let spotify_auth = AuthCodeSpotify::with_config(creds, oauth, config);
+    // optional, for running against fishify-fake-api
+    if let Ok(api_url) = var("SPOTIFY_API_URL") {
+        config.prefix = api_url;
+    }
+    let mut spotify_auth = Client::new(auth_mode, creds.clone(), oauth.clone(), config.clone());
+    // where accounts linked with /link keep their tokens, next to CACHE_PATH by default
+    let sessions_dir: PathBuf = match var("SESSIONS_DIR") {
+        Ok(dir) => dir.into(),
+        Err(_) => config.cache_path.parent().unwrap_or(&config.cache_path).join("sessions"),
+    };
+    // spotify redirects to the bot after someone opens a /link url
+    let callback_port = auth::redirect_port(&oauth.redirect_uri).expect("`RSPOTIFY_REDIRECT_URI` must be a url");
+    // loopback unless browsers reach the bot over the network, then 0.0.0.0
+    let callback_address = var("CALLBACK_ADDRESS").unwrap_or("127.0.0.1".to_string());
+    let max_context_items = match var("MAX_CONTEXT_ITEMS") {
+        Ok(max) => Some(max.parse().expect("`MAX_CONTEXT_ITEMS` must be a number")),
+        Err(_) => None,
+    };
 
-    spotify_auth.refresh_token().await?;
-    if spotify_auth.get_token().lock().await.unwrap().is_none() {
-        let url = spotify_auth.get_authorize_url(false).unwrap();
-        spotify_auth.prompt_for_token(&url).await.unwrap();
+    let device = var("SPOTIFY_DEVICE").ok();
+    // SPOTIFY_CALLS_PER_MINUTE limits requests to spotify from everyone combined
+    let call_limit = var("SPOTIFY_CALLS_PER_MINUTE").ok()
+        .map(|x| x.parse().expect("`SPOTIFY_CALLS_PER_MINUTE` must be a number"))
+        .map(|calls| Arc::new(CallLimit::new(calls, std::time::Duration::from_secs(60))));
+    let limits = Limits::from_env();
+    let permissions_path: Option<PathBuf> = var("PERMISSIONS_PATH").ok().map(PathBuf::from);
+    let permissions = match &permissions_path {
+        Some(path) => Permissions::load(path)?,
+        None => Permissions::default(),
+    };
+
+    // logging in prints a url and waits for spotify's redirect on RSPOTIFY_REDIRECT_URI's port. When that
+    // can't reach the bot, the url it redirected to can be passed in SPOTIFY_REDIRECTED_URL on the next start
+    let pending_path = config.cache_path.with_extension("pending.json");
+    if !spotify_auth.cached_login().await? {
+        match &mut spotify_auth {
+            Client::Code(spotify) => login(spotify, callback_port, &pending_path).await?,
+            Client::Pkce(spotify) => login(spotify, callback_port, &pending_path).await?,
+        }
     }
+    let sessions = Arc::new(Sessions::new(spotify_auth, SessionOptions {
+        auth: auth_mode,
+        creds: creds,
+        oauth: oauth,
+        config: config,
+        dir: sessions_dir,
+        device: device.clone(),
+        max_context_items: max_context_items,
+        call_limit: call_limit,
+    }).await?);
+    tokio::spawn(sessions::serve_callbacks(sessions.clone(), callback_address, callback_port));
 
     // discord
     let options = poise::FrameworkOptions {
-        // commands go here
+        // commands go here, each with the category permissions are given by. The owner-only ones have none
         commands: vec![
             commands::register(),
-            commands::play(),
-            commands::queue(),
-            commands::search(),
-            commands::queue_list(),
-            commands::pause(),
-            commands::skip(),
-            commands::status(),
-            commands::device_list(),
-            commands::device_connect(),
-            commands::device_status(),
-            commands::set_volume(),
-            commands::set_shuffle(),
-            commands::set_repeat(),
+            categorized(commands::play(), Category::Playback),
+            categorized(commands::queue(), Category::Queue),
+            categorized(commands::search(), Category::ReadOnly),
+            categorized(commands::queue_list(), Category::ReadOnly),
+            categorized(commands::queue_remove(), Category::Queue),
+            categorized(commands::pause(), Category::Playback),
+            categorized(commands::skip(), Category::Playback),
+            categorized(commands::previous(), Category::Playback),
+            categorized(commands::seek(), Category::Playback),
+            categorized(commands::restart(), Category::Playback),
+            categorized(commands::like(), Category::Library),
+            categorized(commands::unlike(), Category::Library),
+            categorized(commands::library_tracks(), Category::ReadOnly),
+            categorized(commands::library_albums(), Category::ReadOnly),
+            categorized(commands::library_shows(), Category::ReadOnly),
+            categorized(commands::play_liked(), Category::Playback),
+            categorized(commands::playlist_create(), Category::Library),
+            categorized(commands::playlist_add(), Category::Library),
+            categorized(commands::playlist_remove(), Category::Library),
+            categorized(commands::playlist_move(), Category::Library),
+            categorized(commands::playlist_rename(), Category::Library),
+            categorized(commands::playlist_list(), Category::ReadOnly),
+            categorized(commands::playlist_show(), Category::ReadOnly),
+            categorized(commands::status(), Category::ReadOnly),
+            categorized(commands::player(), Category::Playback),
+            categorized(commands::device_list(), Category::ReadOnly),
+            categorized(commands::device_connect(), Category::Device),
+            categorized(commands::device_status(), Category::ReadOnly),
+            categorized(commands::set_volume(), Category::Settings),
+            categorized(commands::set_shuffle(), Category::Settings),
+            categorized(commands::set_repeat(), Category::Settings),
+            commands::permissions_role(),
+            commands::permissions_channel(),
+            commands::permissions_ban(),
+            commands::permissions_show(),
+            commands::dj_role(),
+            commands::dj_mode(),
+            commands::vote_skip(),
+            categorized(commands::link(), Category::Account),
+            categorized(commands::link_me(), Category::Account),
+            categorized(commands::unlink(), Category::Account),
+            categorized(commands::unlink_me(), Category::Account),
         ],
         on_error: |error| Box::pin(on_error(error)),
         pre_command: |ctx| {
             Box::pin(async move {
                 println!("Executing command {}...", ctx.command().qualified_name);
+                ctx.data().active_users.lock().unwrap()
+                    .entry(commands::guild_or_user(ctx)).or_default()
+                    .insert(ctx.author().id, Instant::now());
             })
         },
         post_command: |ctx| {
@@ -97,16 +255,10 @@ async fn main() -> Result<()> {
                 println!("Executed command {}!", ctx.command().qualified_name);
             })
         },
-        // use this for permissions at some point
-        //command_check: Some(|ctx| {
-        //    Box::pin(async move {
-        //        if ctx.author().id == 123456789 {
-        //            return Ok(false);
-        //        }
-        //        Ok(true)
-        //    })
-        //}),
-        //skip_checks_for_owners: false,
+        command_check: Some(|ctx| Box::pin(async move {
+            Ok(permissions::check(ctx).await? && limits::check(ctx).await?)
+        })),
+        skip_checks_for_owners: true,
         event_handler: |_ctx, event, _framework, _data| {
             Box::pin(async move {
                 println!("Got an event in event handler: {:?}", event.name());
@@ -126,7 +278,15 @@ async fn main() -> Result<()> {
                 println!("Logged in as {}", _ready.user.name);
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                 Ok(Data {
-                    spotify: spotify_auth,
+                    sessions: sessions,
+                    max_context_items: max_context_items,
+                    device: device,
+                    recent_queries: Mutex::new(HashMap::new()),
+                    query_cache: Mutex::new(HashMap::new()),
+                    permissions: RwLock::new(permissions),
+                    permissions_path: permissions_path,
+                    active_users: Mutex::new(HashMap::new()),
+                    limits: limits,
                 })
             })
         })
@@ -0,0 +1,188 @@
+use crate::{
+    Context, Data,
+    dj::Dj,
+};
+
+use std::{
+    collections::{ HashMap, HashSet, },
+    fmt, fs,
+    path::Path,
+};
+use anyhow::{ Error, Result, };
+use serde::{ Deserialize, Serialize, };
+use poise::serenity_prelude::{ ChannelId, GuildId, RoleId, UserId, };
+
+// What a command does to the shared spotify account, so access can be given per kind of command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, poise::ChoiceParameter)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    // looking without touching: status, queue_list, search and listings
+    ReadOnly,
+    Playback,
+    Queue,
+    Device,
+    Settings,
+    // liked songs and playlists
+    Library,
+    // linking spotify accounts
+    Account,
+}
+
+// tags a command with its category for command_check. Commands without one are refused, except the
+// owner-only ones, which poise already keeps to the owners
+pub fn categorized(mut command: poise::Command<Data, Error>, category: Category) -> poise::Command<Data, Error> {
+    command.custom_data = Box::new(category);
+    command
+}
+
+// Who can use a category of commands and where. An empty list allows everyone or everywhere
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rule {
+    pub roles: HashSet<u64>,
+    pub channels: HashSet<u64>,
+}
+
+// Loaded from and saved to PERMISSIONS_PATH, by guild id. Without rules, anyone can use anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Permissions {
+    pub guilds: HashMap<u64, GuildPermissions>,
+}
+
+// One guild's rules, bans and djs. Dms have none
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildPermissions {
+    pub rules: HashMap<Category, Rule>,
+    pub banned: HashSet<u64>,
+    pub dj: Dj,
+}
+
+// why a command or button was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denied {
+    Banned,
+    Role(Category),
+    Channel(Category),
+    DjOnly,
+    Uncategorized,
+}
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Banned => write!(f, "You're banned from using this bot"),
+            Self::Role(category) => write!(f, "You don't have a role allowed to use {category:?} commands"),
+            Self::Channel(category) => write!(f, "{category:?} commands can't be used in this channel"),
+            Self::DjOnly => write!(f, "Only djs can do that right now, but you can still queue"),
+            Self::Uncategorized => write!(f, "This command isn't in a category, so nobody can use it"),
+        }
+    }
+}
+
+impl std::error::Error for Denied {}
+
+impl Permissions {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_yaml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    // a guild's permissions, or none at all in dms
+    pub fn guild(&self, guild: Option<GuildId>) -> GuildPermissions {
+        guild.and_then(|guild| self.guilds.get(&guild.0)).cloned().unwrap_or_default()
+    }
+
+    pub fn guild_mut(&mut self, guild: GuildId) -> &mut GuildPermissions {
+        self.guilds.entry(guild.0).or_default()
+    }
+}
+
+impl GuildPermissions {
+    // whether a user can run a command of a category here. Bans cover every command
+    pub fn check(&self, command: &str, category: Option<Category>, user: UserId, roles: &[RoleId], channel: ChannelId) -> Result<(), Denied> {
+        if self.banned.contains(&user.0) {
+            return Err(Denied::Banned);
+        }
+        let category = category.ok_or(Denied::Uncategorized)?;
+        if !self.dj.allows(command, roles) {
+            return Err(Denied::DjOnly);
+        }
+        let rule = match self.rules.get(&category) {
+            Some(rule) => rule,
+            None => return Ok(()),
+        };
+        if !rule.roles.is_empty() && !roles.iter().any(|role| rule.roles.contains(&role.0)) {
+            return Err(Denied::Role(category));
+        }
+        if !rule.channels.is_empty() && !rule.channels.contains(&channel.0) {
+            return Err(Denied::Channel(category));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for GuildPermissions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut lines = vec![];
+        for (category, rule) in &self.rules {
+            let roles: Vec<String> = rule.roles.iter().map(|x| format!("<@&{x}>")).collect();
+            let channels: Vec<String> = rule.channels.iter().map(|x| format!("<#{x}>")).collect();
+            lines.push(format!(
+                "{:?}: roles {}, channels {}",
+                category,
+                if roles.is_empty() { "any".to_string() } else { roles.join(" ") },
+                if channels.is_empty() { "any".to_string() } else { channels.join(" ") },
+            ));
+        }
+        if !self.banned.is_empty() {
+            let banned: Vec<String> = self.banned.iter().map(|x| format!("<@{x}>")).collect();
+            lines.push(format!("Banned: {}", banned.join(" ")));
+        }
+        if !self.dj.roles.is_empty() {
+            let roles: Vec<String> = self.dj.roles.iter().map(|x| format!("<@&{x}>")).collect();
+            lines.push(format!("Djs: {}", roles.join(" ")));
+        }
+        if self.dj.dj_only {
+            lines.push("Dj mode: only djs can play and change settings".to_string());
+        }
+        if let Some(fraction) = self.dj.vote_skip {
+            lines.push(format!("Vote skip: {:.0}% of listeners", fraction * 100.0));
+        }
+        if lines.is_empty() {
+            return write!(f, "Everyone can use every command");
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+// poise's command_check, with the category the command was registered with
+pub async fn check(ctx: Context<'_>) -> Result<bool> {
+    let roles = author_roles(ctx).await;
+    let category = ctx.command().custom_data.downcast_ref::<Category>().copied();
+    let permissions = ctx.data().permissions.read().unwrap().guild(ctx.guild_id());
+    permissions.check(&ctx.command().name, category, ctx.author().id, &roles, ctx.channel_id())?;
+    Ok(true)
+}
+
+// poise doesn't run command_check before autocomplete, so autocomplete callbacks check for themselves
+// and suggest nothing to whoever can't use the command
+pub async fn allowed(ctx: Context<'_>) -> bool {
+    check(ctx).await.unwrap_or(false)
+}
+
+// none outside of guilds
+pub async fn author_roles(ctx: Context<'_>) -> Vec<RoleId> {
+    match ctx.author_member().await {
+        Some(member) => member.roles.clone(),
+        None => vec![],
+    }
+}
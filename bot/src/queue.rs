@@ -0,0 +1,295 @@
+use std::{
+    collections::VecDeque,
+    sync::{ Mutex, Weak, },
+    time::{ Duration, SystemTime, UNIX_EPOCH, },
+};
+use anyhow::Result;
+use fishify_lib::{
+    backend::SpotifyBackend,
+    response::Item,
+    retry::Retry,
+    spotify::Fishify,
+};
+use poise::serenity_prelude::UserId;
+
+// how often the feeder checks whether spotify is ready for the next entry
+const FEED_POLL: Duration = Duration::from_secs(5);
+// how many times spotify can refuse an entry before it's dropped, so one bad entry doesn't block the queue
+const MAX_FEED_FAILURES: u32 = 3;
+
+// Something someone queued through the bot, kept here until it's their turn
+#[derive(Debug, Clone)]
+pub struct Entry {
+    // for removing it, unique for as long as the bot runs
+    pub id: u64,
+    pub user: UserId,
+    pub added: SystemTime,
+    pub item: Item,
+    pub uri: String,
+    // the device it was queued for, the feeder's own if none
+    pub device: Option<String>,
+    // how many times spotify wouldn't take it
+    failures: u32,
+}
+
+impl Entry {
+    // a discord timestamp, shown like "5 minutes ago"
+    pub fn added_ago(&self) -> String {
+        let secs = self.added.duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or_default();
+        format!("<t:{secs}:R>")
+    }
+}
+
+// The bot's queue. Entries go to spotify's queue one at a time, taking turns between users, so
+// someone queueing twenty tracks doesn't push everyone else's back by twenty
+#[derive(Debug, Default)]
+pub struct SharedQueue {
+    entries: VecDeque<Entry>,
+    next_id: u64,
+    // whose entry went to spotify last, so the next one is someone else's
+    last_user: Option<UserId>,
+    // in spotify's queue, and not playing yet
+    pub fed: Option<Entry>,
+    // what's playing, if the bot queued it
+    pub playing: Option<Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoveError {
+    NotFound(u64),
+    NotYours(u64),
+}
+
+impl std::fmt::Display for RemoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "Nothing waiting in the queue with id {id}"),
+            Self::NotYours(id) => write!(f, "Queue entry {id} was added by someone else"),
+        }
+    }
+}
+
+impl std::error::Error for RemoveError {}
+
+impl SharedQueue {
+    pub fn push(&mut self, user: UserId, item: Item, uri: String, device: Option<String>) -> Entry {
+        self.next_id += 1;
+        let entry = Entry { id: self.next_id, user, added: SystemTime::now(), item, uri, device, failures: 0 };
+        self.entries.push_back(entry.clone());
+        entry
+    }
+
+    // removes a waiting entry, only the user who added it can unless `any` is set
+    pub fn remove(&mut self, id: u64, user: UserId, any: bool) -> Result<Entry, RemoveError> {
+        let index = self.entries.iter().position(|x| x.id == id).ok_or(RemoveError::NotFound(id))?;
+        if !any && self.entries[index].user != user {
+            return Err(RemoveError::NotYours(id));
+        }
+        Ok(self.entries.remove(index).unwrap())
+    }
+
+    pub fn user_entries(&self, user: UserId) -> Vec<&Entry> {
+        self.entries.iter().filter(|x| x.user == user).collect()
+    }
+
+    // waiting entries in the order they'll go to spotify: everyone's oldest entry, starting after
+    // whoever went last, then everyone's second oldest, and so on
+    pub fn ordered(&self) -> Vec<&Entry> {
+        let mut users: Vec<UserId> = vec![];
+        for entry in &self.entries {
+            if !users.contains(&entry.user) {
+                users.push(entry.user);
+            }
+        }
+        if let Some(last) = self.last_user.and_then(|last| users.iter().position(|x| *x == last)) {
+            users.rotate_left(last + 1);
+        }
+
+        let mut per_user: Vec<VecDeque<&Entry>> = users.iter()
+            .map(|user| self.entries.iter().filter(|x| x.user == *user).collect())
+            .collect();
+        let mut ordered = vec![];
+        while per_user.iter().any(|x| !x.is_empty()) {
+            for entries in per_user.iter_mut() {
+                if let Some(entry) = entries.pop_front() {
+                    ordered.push(entry);
+                }
+            }
+        }
+        ordered
+    }
+
+    // position starts at 1
+    pub fn position(&self, id: u64) -> Option<usize> {
+        self.ordered().iter().position(|x| x.id == id).map(|x| x + 1)
+    }
+
+    fn pop_next(&mut self) -> Option<Entry> {
+        let id = self.ordered().first()?.id;
+        let index = self.entries.iter().position(|x| x.id == id)?;
+        let entry = self.entries.remove(index)?;
+        self.last_user = Some(entry.user);
+        Some(entry)
+    }
+
+    // puts an entry back at the front when spotify wouldn't take it, giving the turn back to whoever
+    // had it before. false if it failed too often and was dropped instead
+    fn requeue(&mut self, mut entry: Entry, last_user: Option<UserId>) -> bool {
+        self.last_user = last_user;
+        entry.failures += 1;
+        if entry.failures >= MAX_FEED_FAILURES {
+            return false;
+        }
+        self.entries.push_front(entry);
+        true
+    }
+}
+
+// Runs for as long as the session does, keeping one of the bot's entries in spotify's queue
+pub async fn feed(spotify: Weak<Retry<dyn SpotifyBackend>>, queue: Weak<Mutex<SharedQueue>>, device: Option<String>, max_items: Option<u32>) {
+    loop {
+        tokio::time::sleep(FEED_POLL).await;
+        // gone once the session is unlinked
+        let (spotify, queue) = match (spotify.upgrade(), queue.upgrade()) {
+            (Some(spotify), Some(queue)) => (spotify, queue),
+            _ => return,
+        };
+        let fishify = Fishify::from(spotify.as_ref()).with_device(device.clone()).with_max_items(max_items);
+        if let Err(err) = feed_next(&fishify, &queue).await {
+            println!("Error while feeding the queue: {:?}", err);
+        }
+    }
+}
+
+async fn feed_next(fishify: &Fishify<'_>, queue: &Mutex<SharedQueue>) -> Result<()> {
+    let fed = queue.lock().unwrap().fed.clone();
+    if let Some(fed) = fed {
+        let listing = fishify.queue_list().await?;
+        let playing = listing.currently_playing.as_ref().and_then(|x| x.uri.as_deref());
+        let mut queue = queue.lock().unwrap();
+        if playing == Some(fed.uri.as_str()) {
+            queue.playing = queue.fed.take();
+        } else if listing.queue.iter().any(|x| x.uri.as_deref() == Some(fed.uri.as_str())) {
+            // still waiting its turn in spotify's queue
+            return Ok(());
+        } else {
+            // skipped past
+            queue.fed = None;
+        }
+    }
+
+    let (next, last_user) = {
+        let mut queue = queue.lock().unwrap();
+        let last_user = queue.last_user;
+        match queue.pop_next() {
+            Some(next) => (next, last_user),
+            None => return Ok(()),
+        }
+    };
+    let target = match &next.device {
+        Some(device) => fishify.clone().with_device(Some(device.clone())),
+        None => fishify.clone(),
+    };
+    match target.play_uri(&next.uri, true).await {
+        Ok(_) => queue.lock().unwrap().fed = Some(next),
+        Err(err) => {
+            let (id, uri) = (next.id, next.uri.clone());
+            if !queue.lock().unwrap().requeue(next, last_user) {
+                println!("Dropped queue entry {id} ({uri}) after {MAX_FEED_FAILURES} failures");
+            }
+            return Err(err.into());
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: UserId = UserId(1);
+    const BOB: UserId = UserId(2);
+    const CAROL: UserId = UserId(3);
+
+    fn push(queue: &mut SharedQueue, user: UserId, name: &str) -> u64 {
+        let item = Item {
+            name: name.to_string(),
+            artist: None,
+            uri: Some(format!("spotify:track:{name}")),
+            duration: None,
+            url: None,
+            artist_url: None,
+            image: None,
+        };
+        queue.push(user, item, format!("spotify:track:{name}"), None).id
+    }
+
+    fn names(queue: &SharedQueue) -> Vec<String> {
+        queue.ordered().iter().map(|x| x.item.name.clone()).collect()
+    }
+
+    #[test]
+    fn ordered_takes_turns() {
+        let mut queue = SharedQueue::default();
+        push(&mut queue, ALICE, "a1");
+        push(&mut queue, ALICE, "a2");
+        push(&mut queue, ALICE, "a3");
+        push(&mut queue, BOB, "b1");
+        push(&mut queue, CAROL, "c1");
+        push(&mut queue, BOB, "b2");
+        assert_eq!(names(&queue), ["a1", "b1", "c1", "a2", "b2", "a3"]);
+        assert_eq!(queue.position(queue.user_entries(BOB)[1].id), Some(5));
+
+        // whoever went last waits for everyone else
+        assert_eq!(queue.pop_next().unwrap().item.name, "a1");
+        assert_eq!(names(&queue), ["b1", "c1", "a2", "b2", "a3"]);
+        assert_eq!(queue.pop_next().unwrap().item.name, "b1");
+        assert_eq!(names(&queue), ["c1", "a2", "b2", "a3"]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut queue = SharedQueue::default();
+        let first = push(&mut queue, ALICE, "a1");
+        let second = push(&mut queue, BOB, "b1");
+
+        assert_eq!(queue.remove(first, BOB, false).unwrap_err(), RemoveError::NotYours(first));
+        assert_eq!(queue.remove(99, ALICE, false).unwrap_err(), RemoveError::NotFound(99));
+        assert_eq!(queue.remove(first, ALICE, false).unwrap().item.name, "a1");
+        assert_eq!(queue.remove(first, ALICE, false).unwrap_err(), RemoveError::NotFound(first));
+        // someone allowed to remove anything
+        assert_eq!(queue.remove(second, ALICE, true).unwrap().item.name, "b1");
+        assert!(queue.ordered().is_empty());
+    }
+
+    #[test]
+    fn requeue_keeps_turn() {
+        let mut queue = SharedQueue::default();
+        push(&mut queue, ALICE, "a1");
+        push(&mut queue, BOB, "b1");
+        push(&mut queue, ALICE, "a2");
+
+        let last_user = queue.last_user;
+        let next = queue.pop_next().unwrap();
+        assert!(queue.requeue(next, last_user));
+        assert_eq!(queue.last_user, None);
+        assert_eq!(names(&queue), ["a1", "b1", "a2"]);
+    }
+
+    #[test]
+    fn requeue_drops_after_failures() {
+        let mut queue = SharedQueue::default();
+        push(&mut queue, ALICE, "a1");
+        push(&mut queue, BOB, "b1");
+
+        for _ in 1..MAX_FEED_FAILURES {
+            let next = queue.pop_next().unwrap();
+            assert_eq!(next.item.name, "a1");
+            assert!(queue.requeue(next, None));
+        }
+        let next = queue.pop_next().unwrap();
+        assert_eq!(next.failures, MAX_FEED_FAILURES - 1);
+        assert!(!queue.requeue(next, None));
+        assert_eq!(names(&queue), ["b1"]);
+    }
+}
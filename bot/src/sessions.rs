@@ -0,0 +1,350 @@
+use crate::{
+    dj::Vote,
+    queue::{ self, SharedQueue, },
+};
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::PathBuf,
+    sync::{ Arc, Mutex, RwLock, },
+    time::{ Duration, Instant, },
+};
+use anyhow::{ anyhow, Result, };
+use fishify_lib::{
+    auth::{ self, Callback, LoginClient, },
+    backend::SpotifyBackend,
+    config::AuthMode,
+    retry::{ CallLimit, Retry, },
+};
+use poise::serenity_prelude::{ GuildId, UserId, };
+use rspotify::{
+    Credentials, OAuth, Config,
+    AuthCodeSpotify, AuthCodePkceSpotify,
+    clients::OAuthClient,
+};
+use tokio::net::TcpListener;
+
+// how long a /link url works for
+const LINK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// Whose spotify account a session plays on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Owner {
+    Guild(GuildId),
+    User(UserId),
+}
+
+impl Owner {
+    fn cache_file(&self) -> String {
+        match self {
+            Self::Guild(id) => format!("guild-{}.json", id.0),
+            Self::User(id) => format!("user-{}.json", id.0),
+        }
+    }
+
+    fn from_cache_file(name: &str) -> Option<Self> {
+        let (kind, id) = name.strip_suffix(".json")?.split_once('-')?;
+        let id: u64 = id.parse().ok()?;
+        match kind {
+            "guild" => Some(Self::Guild(GuildId(id))),
+            "user" => Some(Self::User(UserId(id))),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Guild(_) => write!(f, "this server"),
+            Self::User(_) => write!(f, "you"),
+        }
+    }
+}
+
+// A client for one account, with a client secret or with pkce, whichever SPOTIFY_AUTH says
+pub enum Client {
+    Code(AuthCodeSpotify),
+    Pkce(AuthCodePkceSpotify),
+}
+
+impl Client {
+    pub fn new(auth: AuthMode, creds: Credentials, oauth: OAuth, config: Config) -> Self {
+        match auth {
+            AuthMode::Code => Self::Code(AuthCodeSpotify::with_config(creds, oauth, config)),
+            AuthMode::Pkce => Self::Pkce(AuthCodePkceSpotify::with_config(creds, oauth, config)),
+        }
+    }
+
+    pub async fn cached_login(&self) -> Result<bool> {
+        match self {
+            Self::Code(spotify) => auth::cached_login(spotify).await,
+            Self::Pkce(spotify) => auth::cached_login(spotify).await,
+        }
+    }
+
+    // the authorize url, and the oauth state and pkce verifier the redirect is finished with
+    fn authorize(&mut self) -> Result<(String, String, Option<String>)> {
+        match self {
+            Self::Code(spotify) => Ok((spotify.authorize_url()?, spotify.oauth.state.clone(), None)),
+            Self::Pkce(spotify) => Ok((spotify.authorize_url()?, spotify.oauth.state.clone(), spotify.verifier())),
+        }
+    }
+
+    async fn request_token(&mut self, code: &str, verifier: Option<String>) -> Result<()> {
+        match self {
+            Self::Code(spotify) => spotify.request_token(code).await?,
+            Self::Pkce(spotify) => {
+                spotify.set_verifier(verifier);
+                spotify.request_token(code).await?
+            },
+        }
+        Ok(())
+    }
+
+    fn into_backend(self) -> Box<dyn SpotifyBackend> {
+        match self {
+            Self::Code(spotify) => Box::new(spotify),
+            Self::Pkce(spotify) => Box::new(spotify),
+        }
+    }
+}
+
+// One spotify account with its own bot queue and skip votes
+pub struct Session {
+    pub spotify: Arc<Retry<dyn SpotifyBackend>>,
+    pub queue: Arc<Mutex<SharedQueue>>,
+    pub votes: Mutex<Vote>,
+}
+
+impl Session {
+    // starts feeding the session's queue, until the session is dropped
+    fn start(spotify: Client, options: &SessionOptions) -> Self {
+        let spotify = Arc::new(Retry::boxed(spotify.into_backend()).with_device(options.device.clone()).with_call_limit(options.call_limit.clone()));
+        let queue = Arc::new(Mutex::new(SharedQueue::default()));
+        tokio::spawn(queue::feed(Arc::downgrade(&spotify), Arc::downgrade(&queue), options.device.clone(), options.max_context_items));
+        return Self {
+            spotify: spotify,
+            queue: queue,
+            votes: Mutex::new(Vote::default()),
+        };
+    }
+}
+
+// What every session is made with, linked accounts only differ in their token cache
+pub struct SessionOptions {
+    pub auth: AuthMode,
+    pub creds: Credentials,
+    pub oauth: OAuth,
+    pub config: Config,
+    // where linked accounts' tokens are cached, one file each
+    pub dir: PathBuf,
+    pub device: Option<String>,
+    pub max_context_items: Option<u32>,
+    // shared by every session, spotify limits the app rather than each account
+    pub call_limit: Option<Arc<CallLimit>>,
+}
+
+impl SessionOptions {
+    // a client with a fresh oauth state, caching its token in the owner's file
+    fn client(&self, owner: &Owner) -> Client {
+        let oauth = OAuth { state: OAuth::default().state, ..self.oauth.clone() };
+        let config = Config { cache_path: self.dir.join(owner.cache_file()), ..self.config.clone() };
+        Client::new(self.auth, self.creds.clone(), oauth, config)
+    }
+}
+
+// The bot's default account, plus the accounts guilds and users linked with /link
+pub struct Sessions {
+    default: Arc<Session>,
+    linked: RwLock<HashMap<Owner, Arc<Session>>>,
+    // /link urls waiting for spotify's redirect, by oauth state, with pkce's verifier
+    pending: Mutex<HashMap<String, (Owner, Instant, Option<String>)>>,
+    options: SessionOptions,
+}
+
+impl Sessions {
+    // picks up every account linked before the bot restarted
+    pub async fn new(default: Client, options: SessionOptions) -> Result<Self> {
+        fs::create_dir_all(&options.dir)?;
+        let mut linked = HashMap::new();
+        for file in fs::read_dir(&options.dir)? {
+            let file = file?;
+            let owner = match file.file_name().to_str().and_then(Owner::from_cache_file) {
+                Some(owner) => owner,
+                None => continue,
+            };
+            let spotify = options.client(&owner);
+            if !spotify.cached_login().await? {
+                println!("Couldn't log in with {}, it has to be linked again", file.path().display());
+                continue;
+            }
+            linked.insert(owner, Arc::new(Session::start(spotify, &options)));
+        }
+
+        return Ok(Self {
+            default: Arc::new(Session::start(default, &options)),
+            linked: RwLock::new(linked),
+            pending: Mutex::new(HashMap::new()),
+            options: options,
+        });
+    }
+
+    // the user's own account, then the guild's, then the bot's
+    pub fn get(&self, guild: Option<GuildId>, user: UserId) -> Arc<Session> {
+        let linked = self.linked.read().unwrap();
+        linked.get(&Owner::User(user))
+            .or(guild.and_then(|guild| linked.get(&Owner::Guild(guild))))
+            .unwrap_or(&self.default)
+            .clone()
+    }
+
+    // a url for authorizing fishify, which redirects back to serve_callbacks
+    pub fn link_url(&self, owner: Owner) -> Result<String> {
+        let (url, state, verifier) = self.options.client(&owner).authorize()?;
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, (_, started, _)| started.elapsed() < LINK_TIMEOUT);
+        pending.insert(state, (owner, Instant::now(), verifier));
+        Ok(url)
+    }
+
+    // exchanges the code spotify redirected with for a token, replacing any earlier session
+    pub async fn finish_link(&self, callback: &Callback) -> Result<Owner> {
+        let (owner, verifier) = self.take_pending(callback.state.as_deref())?;
+        let mut spotify = self.options.client(&owner);
+        spotify.request_token(&callback.code, verifier).await?;
+        self.linked.write().unwrap().insert(owner, Arc::new(Session::start(spotify, &self.options)));
+        Ok(owner)
+    }
+
+    // who a redirect's link was for, and pkce's verifier. Each link only works once
+    fn take_pending(&self, state: Option<&str>) -> Result<(Owner, Option<String>)> {
+        let pending = state.and_then(|state| self.pending.lock().unwrap().remove(state));
+        match pending {
+            Some((owner, started, verifier)) if started.elapsed() < LINK_TIMEOUT => Ok((owner, verifier)),
+            _ => Err(anyhow!("This link expired or was already used, run /link again")),
+        }
+    }
+
+    // goes back to the bot's account, forgetting the token. false if nothing was linked
+    pub fn unlink(&self, owner: Owner) -> Result<bool> {
+        let removed = self.linked.write().unwrap().remove(&owner).is_some();
+        let path = self.options.dir.join(owner.cache_file());
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(removed)
+    }
+}
+
+// Answers spotify's redirects after someone opens a /link url, for as long as the bot runs. Only
+// listens on `address`, loopback unless the browsers opening /link urls reach the bot over the network
+pub async fn serve_callbacks(sessions: Arc<Sessions>, address: String, port: u16) {
+    let listener = match TcpListener::bind((address.as_str(), port)).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            println!("Couldn't listen for /link redirects on {address}:{port}: {err}");
+            return;
+        },
+    };
+    let mut callbacks = auth::accept_callbacks(listener);
+    while let Some((callback, stream)) = callbacks.recv().await {
+        // exchanging the code waits on spotify, so each link finishes on its own task
+        let sessions = sessions.clone();
+        tokio::spawn(async move {
+            let response = match callback {
+                Ok(callback) => match sessions.finish_link(&callback).await {
+                    Ok(owner) => auth::respond(stream, true, &format!("The bot now plays on your spotify account for {owner}")).await,
+                    Err(err) => auth::respond(stream, false, &err.to_string()).await,
+                },
+                Err(error) => auth::respond(stream, false, &format!("Spotify said: {error}")).await,
+            };
+            if let Err(err) = response {
+                println!("Error while answering a /link redirect: {:?}", err);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: GuildId = GuildId(1);
+    const USER: UserId = UserId(2);
+
+    async fn sessions(name: &str) -> Sessions {
+        let dir = std::env::temp_dir().join(format!("fishicord-sessions-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let options = SessionOptions {
+            auth: AuthMode::Code,
+            creds: Credentials::new("id", "secret"),
+            oauth: OAuth { redirect_uri: "http://127.0.0.1:8888/callback".to_string(), ..Default::default() },
+            config: Config::default(),
+            dir: dir,
+            device: None,
+            max_context_items: None,
+            call_limit: None,
+        };
+        let default = options.client(&Owner::User(UserId(0)));
+        Sessions::new(default, options).await.unwrap()
+    }
+
+    // a linked account, without going through spotify
+    fn link(sessions: &Sessions, owner: Owner) -> Arc<Session> {
+        let session = Arc::new(Session::start(sessions.options.client(&owner), &sessions.options));
+        sessions.linked.write().unwrap().insert(owner, session.clone());
+        session
+    }
+
+    #[tokio::test]
+    async fn get_precedence() {
+        let sessions = sessions("get").await;
+        let default = sessions.get(Some(GUILD), USER);
+        assert!(Arc::ptr_eq(&default, &sessions.default));
+
+        let guild = link(&sessions, Owner::Guild(GUILD));
+        assert!(Arc::ptr_eq(&sessions.get(Some(GUILD), USER), &guild));
+        // not in dms, or other guilds
+        assert!(Arc::ptr_eq(&sessions.get(None, USER), &default));
+        assert!(Arc::ptr_eq(&sessions.get(Some(GuildId(3)), USER), &default));
+
+        let user = link(&sessions, Owner::User(USER));
+        assert!(Arc::ptr_eq(&sessions.get(Some(GUILD), USER), &user));
+        assert!(Arc::ptr_eq(&sessions.get(None, USER), &user));
+        assert!(Arc::ptr_eq(&sessions.get(Some(GUILD), UserId(4)), &guild));
+
+        assert!(sessions.unlink(Owner::User(USER)).unwrap());
+        assert!(!sessions.unlink(Owner::User(USER)).unwrap());
+        assert!(Arc::ptr_eq(&sessions.get(Some(GUILD), USER), &guild));
+    }
+
+    #[tokio::test]
+    async fn pending_once() {
+        let sessions = sessions("once").await;
+        sessions.link_url(Owner::User(USER)).unwrap();
+        let state = sessions.pending.lock().unwrap().keys().next().unwrap().clone();
+
+        assert!(sessions.take_pending(None).is_err());
+        assert!(sessions.take_pending(Some("unknown")).is_err());
+        assert_eq!(sessions.take_pending(Some(&state)).unwrap(), (Owner::User(USER), None));
+        // reused
+        assert!(sessions.take_pending(Some(&state)).is_err());
+    }
+
+    #[tokio::test]
+    async fn pending_expires() {
+        let sessions = sessions("expires").await;
+        let started = Instant::now() - LINK_TIMEOUT - Duration::from_secs(1);
+        sessions.pending.lock().unwrap().insert("old".to_string(), (Owner::Guild(GUILD), started, None));
+        assert!(sessions.take_pending(Some("old")).is_err());
+
+        // and is cleared out when the next link starts
+        sessions.pending.lock().unwrap().insert("old".to_string(), (Owner::Guild(GUILD), started, None));
+        sessions.link_url(Owner::User(USER)).unwrap();
+        let pending = sessions.pending.lock().unwrap();
+        assert!(!pending.contains_key("old"));
+        assert_eq!(pending.len(), 1);
+    }
+}
@@ -1,9 +1,11 @@
 use clap::{ 
     builder::{ BoolishValueParser, },
-    Subcommand, Parser, ArgAction,
+    Subcommand, Parser, ArgAction, ValueEnum,
 };
 use clap_complete::{ shells::Shell, };
 
+use fishify_lib::clock::Position;
+
 use rspotify::model::enums::{
     misc::RepeatState,
     types::SearchType,
@@ -45,12 +47,49 @@ fn shell_parser(s: &str) -> Result<Shell, String> {
 #[command(name = "fishify")]
 #[command(about = "A spotify client CLI", long_about = None)]
 pub struct Cli {
+    /// Output format, 'text' for people or 'json'/'yaml' for scripts
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Device id or name to play on, instead of the one in client.yml
+    #[arg(short, long, global = true)]
+    pub device: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Yaml,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Commands {
+    #[command(flatten)]
+    Spotify(SpotifyCommands),
+    /// Log in to spotify again, without needing a browser on this machine
+    Login {
+        /// Url spotify redirected to, to finish a login started with --no-wait
+        #[arg(short, long)]
+        url: Option<String>,
+        /// Print the login url and exit, instead of waiting for the redirect
+        #[arg(long)]
+        no_wait: bool,
+    },
+    /// Generate shell completions
+    Completions {
+        /// Target shell. Shell will be determined from the environment if unspecified.
+        #[arg(short, long, value_parser = shell_parser)]
+        shell: Option<Shell>,
+    }
+}
+
+// The commands that need a logged in client
+#[derive(Debug, Subcommand)]
+pub enum SpotifyCommands {
     /// Play music. Unpause if query is empty
     Play {
         /// Search query for music, or url if --url is supplied
@@ -61,6 +100,12 @@ pub enum Commands {
         /// Type of music to be played, can be 'track', 'album', 'playlist', 'artist', 'episode', or 'show'
         #[arg(short, long, value_parser = type_parser)]
         _type: Option<SearchType>,
+        /// Choose from the top search results instead of playing the first
+        #[arg(short, long)]
+        pick: bool,
+        /// How many search results to choose from with --pick, at most 50
+        #[arg(long, requires = "pick", default_value_t = 10)]
+        count: u32,
     },
     #[command(arg_required_else_help = true, args_conflicts_with_subcommands = true)]
     /// Add music to spotify queue
@@ -73,6 +118,12 @@ pub enum Commands {
         /// Type of music to be played, can be 'track', 'album', 'playlist', 'artist', 'episode', or 'show'
         #[arg(short, long, value_parser = type_parser)]
         _type: Option<SearchType>,
+        /// Choose from the top search results instead of playing the first
+        #[arg(short, long)]
+        pick: bool,
+        /// How many search results to choose from with --pick, at most 50
+        #[arg(long, requires = "pick", default_value_t = 10)]
+        count: u32,
 
         #[command(subcommand)]
         command: Option<QueueCommands>,
@@ -99,24 +150,43 @@ pub enum Commands {
         #[arg(default_value_t = 1)]
         count: u8,  
     },
+    /// Go back to the previous track
+    Prev,
+    #[command(arg_required_else_help = true)]
+    /// Seek within the current track
+    Seek {
+        /// Position, can be absolute '1:30', relative '+15s' or '-30s', or a percentage '50%'
+        #[arg(allow_hyphen_values = true)]
+        position: Position,
+    },
+    /// Restart the current track
+    Restart,
     #[command(arg_required_else_help = true)]
     /// Device commands
     Device {
         #[command(subcommand)]
         command: DeviceCommands,
     },
+    #[command(arg_required_else_help = true)]
+    /// Liked songs and saved music
+    Library {
+        #[command(subcommand)]
+        command: LibraryCommands,
+    },
+    #[command(arg_required_else_help = true)]
+    /// Create and edit playlists
+    Playlist {
+        #[command(subcommand)]
+        command: PlaylistCommands,
+    },
     /// Set Spotify settings
     #[command(arg_required_else_help = true)]
     Set {
         #[command(subcommand)]
         command: SetCommands,
     },
-    /// Generate shell completions
-    Completions {
-        /// Target shell. Shell will be determined from the environment if unspecified.
-        #[arg(short, long, value_parser = shell_parser)]
-        shell: Option<Shell>,
-    }
+    /// Interactive player with now playing, queue, search, and devices
+    Tui,
 }
 
 #[derive(Debug, Subcommand)]
@@ -125,6 +195,101 @@ pub enum QueueCommands {
     List,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum LibraryCommands {
+    /// Like the current track
+    Like,
+    /// Unlike the current track
+    Unlike,
+    /// List liked songs
+    Tracks {
+        /// Limit number of results, defaults to 20
+        #[arg(short, long)]
+        limit: Option<u32>,
+    },
+    /// List saved albums
+    Albums {
+        /// Limit number of results, defaults to 20
+        #[arg(short, long)]
+        limit: Option<u32>,
+    },
+    /// List saved shows
+    Shows {
+        /// Limit number of results, defaults to 20
+        #[arg(short, long)]
+        limit: Option<u32>,
+    },
+    /// Play liked songs
+    Play,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PlaylistCommands {
+    #[command(arg_required_else_help = true)]
+    /// Create a playlist
+    Create {
+        name: String,
+        /// Make the playlist public
+        #[arg(short, long)]
+        public: bool,
+    },
+    #[command(arg_required_else_help = true)]
+    /// Add music to a playlist, the current track if query is empty
+    Add {
+        /// Playlist name, uri, or url
+        playlist: String,
+        /// Search query for music, or url if --url is supplied
+        query: Option<String>,
+        /// Treat query as a url
+        #[arg(short, long)]
+        url: bool,
+        /// Type of music to add, can be 'track' or 'episode'
+        #[arg(short, long, value_parser = type_parser)]
+        _type: Option<SearchType>,
+    },
+    #[command(arg_required_else_help = true)]
+    /// Remove the item at a position from a playlist
+    Remove {
+        /// Playlist name, uri, or url
+        playlist: String,
+        /// Position of the item, as listed by 'playlist show'
+        position: u32,
+    },
+    #[command(arg_required_else_help = true)]
+    /// Move an item to another position
+    Move {
+        /// Playlist name, uri, or url
+        playlist: String,
+        /// Current position of the item
+        from: u32,
+        /// New position of the item
+        to: u32,
+    },
+    #[command(arg_required_else_help = true)]
+    /// Rename a playlist
+    Rename {
+        /// Playlist name, uri, or url
+        playlist: String,
+        /// New name
+        name: String,
+    },
+    /// List your playlists
+    List {
+        /// Limit number of results, defaults to 20
+        #[arg(short, long)]
+        limit: Option<u32>,
+    },
+    #[command(arg_required_else_help = true)]
+    /// Show the contents of a playlist
+    Show {
+        /// Playlist name, uri, or url
+        playlist: String,
+        /// Limit number of results, defaults to 100
+        #[arg(short, long)]
+        limit: Option<u32>,
+    },
+}
+
 #[derive(Debug, Subcommand)]
 pub enum SetCommands {
     #[command(arg_required_else_help = true)]
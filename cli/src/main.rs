@@ -1,17 +1,25 @@
 mod cli;
+mod tui;
 
-use cli::{ Cli, Commands, QueueCommands, DeviceCommands, SetCommands, };
+use cli::{ Cli, Commands, SpotifyCommands, QueueCommands, DeviceCommands, LibraryCommands, PlaylistCommands, SetCommands, OutputFormat, };
 
 use fishify_lib::{
-    spotify_init,
+    spotify_init, spotify_client, spotify_pkce_client,
+    auth::{ self, LoginClient, },
+    config::{ AuthMode, ClientConfig, },
+    error::FishifyError,
+    retry::Retry,
     spotify::{ Fishify, },
+    response::{ Response, },
 };
 
-use std::io;
+use std::io::{ self, Write, };
+use rspotify::model::enums::types::SearchType;
 use anyhow::{ anyhow, Result, };
 use clap::{ Parser, CommandFactory, Command, };
 use clap_complete::{ generate, Shell, };
-use rspotify::{ ClientError, http::HttpError, };
+
+const MAX_PICK_RESULTS: u32 = 50;
 
 fn gen_completions(cli: &mut Command, shell: Option<Shell>) -> Result<()> {
     let sh = shell.unwrap_or(Shell::from_env().ok_or(anyhow!("Could not determine shell"))?);
@@ -19,63 +27,144 @@ fn gen_completions(cli: &mut Command, shell: Option<Shell>) -> Result<()> {
     Ok(())
 }
 
+// lists the top search results on stderr and plays or queues the one typed in
+async fn pick_and_play(spotify: &Fishify<'_>, query: String, _type: Option<SearchType>, count: u32, queue: bool) -> Result<Response> {
+    // spotify returns at most 50 results at a time
+    let results = spotify.search(query, _type, Some(count.clamp(1, MAX_PICK_RESULTS))).await?;
+    if results.items.is_empty() {
+        return Err(FishifyError::NoSearchResults.into());
+    }
+    for (i, item) in results.items.iter().enumerate() {
+        eprintln!("{:>2}. {item}", i + 1);
+    }
+    eprint!("Pick one [1-{}]: ", results.items.len());
+    io::stderr().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    let position: u32 = line.trim().parse().map_err(|_| anyhow!("Not a number: {}", line.trim()))?;
+    let item = position.checked_sub(1)
+        .and_then(|i| results.items.get(i as usize))
+        .ok_or(FishifyError::InvalidPosition(position))?;
+    let uri = item.uri.as_deref().ok_or(anyhow!("{item} has no uri"))?;
+
+    Ok(Response::from(spotify.play_uri(uri, queue).await?))
+}
+
+// starts a login even with a cached token. Without waiting, the url it redirects to is passed to
+// `login --url` afterwards, from wherever the browser was
+async fn login(spotify: &mut impl LoginClient, client_config: &ClientConfig, url: Option<String>, no_wait: bool) -> Result<()> {
+    let pending_path = client_config.get_or_build_paths()?.pending_login_path;
+    match url {
+        Some(url) => auth::finish_login(spotify, &pending_path, &url).await?,
+        None => {
+            let url = auth::start_login(spotify, &pending_path)?;
+            if no_wait {
+                println!("{url}");
+                eprintln!("Open the url, then run `fishify login --url <url it redirected to>`");
+                return Ok(());
+            }
+            eprintln!("Open this url to authorize fishify:\n{url}");
+            auth::wait_for_login(spotify, client_config.get_port(), &pending_path).await?;
+        },
+    }
+    eprintln!("Logged in");
+    Ok(())
+}
+
+fn print_response(response: &Response, output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Text => if response.show() {
+            println!("{response}");
+        },
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(response)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(response)?),
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let spotify_auth = spotify_init().await?;
-    let mut spotify = Fishify::from(&spotify_auth);
+    let cli = Cli::parse();
 
-    'retry: loop {
-        let cli = Cli::parse();
+    let mut client_config = ClientConfig::new();
+    client_config.load_config()?;
+    // logging in and completions don't need a logged in client
+    let command = match cli.command {
+        Commands::Spotify(command) => command,
+        Commands::Login{url, no_wait} => return match client_config.auth {
+            AuthMode::Code => login(&mut spotify_client(&client_config)?, &client_config, url, no_wait).await,
+            AuthMode::Pkce => login(&mut spotify_pkce_client(&client_config)?, &client_config, url, no_wait).await,
+        },
+        Commands::Completions{shell} => return gen_completions(&mut Cli::command(), shell),
+    };
+    let device = cli.device.clone().or(client_config.get_device());
+    let spotify_auth = Retry::boxed(spotify_init().await?)
+        .with_device(device.clone());
+    let fishify = || Fishify::from(&spotify_auth)
+        .with_max_items(client_config.max_context_items)
+        .with_device(device.clone());
+    let spotify = fishify().with_progress(|progress| eprintln!("{progress}"));
 
-        let result = match cli.command {
-            Commands::Play{query, url, _type} => spotify.play(query, _type, url, false).await,
-            Commands::Queue{query, url, _type, command} => {
-                match command {
-                    Some(QueueCommands::List) => spotify.queue_list().await,
-                    None => spotify.play(query, _type, url, true).await,
-                }
-            },
-            Commands::Search{query, _type, limit} => spotify.search(query, _type, limit).await,
-            Commands::Pause => spotify.pause().await,
-            Commands::Skip{count} => spotify.skip(count).await,
-            Commands::Status => spotify.status().await,
-            Commands::Device{command} => {
-                match command {
-                    DeviceCommands::Connect{name} => spotify.device_connect(name).await,
-                    DeviceCommands::List => spotify.device_list().await,
-                    DeviceCommands::Status => spotify.device_status().await,
-                }
+    let response: Response = match command {
+        SpotifyCommands::Play{query, url, _type, pick, count} => {
+            match (query, pick) {
+                (Some(query), true) if !url => pick_and_play(&spotify, query, _type, count, false).await.map_err(FishifyError::from),
+                (query, _) => spotify.play(query, _type, url, false).await.map(Response::from),
             }
-            Commands::Set{command} => {
-                match command {
-                    SetCommands::Volume{level} => spotify.set_volume(level as u8).await,
-                    SetCommands::Shuffle{state} => spotify.set_shuffle(state).await,
-                    SetCommands::Repeat{state} => spotify.set_repeat(state).await,
-                }
+        },
+        SpotifyCommands::Queue{query, url, _type, pick, count, command} => {
+            match (command, query, pick) {
+                (Some(QueueCommands::List), _, _) => spotify.queue_list().await.map(Response::from),
+                (None, Some(query), true) if !url => pick_and_play(&spotify, query, _type, count, true).await.map_err(FishifyError::from),
+                (None, query, _) => spotify.play(query, _type, url, true).await.map(Response::from),
             }
-            Commands::Completions{shell} => gen_completions(&mut Cli::command(), shell),
-        };
-
-        if result.is_ok() { break; }
-        // error handling
-        let err = result.as_ref().err().unwrap();
-
-        if let Some(ClientError::Http(http)) = err.downcast_ref::<ClientError>() {
-            if let HttpError::StatusCode(response) = http.as_ref() {
-                if response.status() == 404 {
-                    if spotify.device_connect(None).await.is_ok() {
-                        continue 'retry;
-                    }
-                }
+        },
+        SpotifyCommands::Search{query, _type, limit} => spotify.search(query, _type, limit).await.map(Response::from),
+        SpotifyCommands::Pause => spotify.pause().await.map(Response::from),
+        SpotifyCommands::Skip{count} => spotify.skip(count).await.map(Response::from),
+        SpotifyCommands::Prev => spotify.previous().await.map(Response::from),
+        SpotifyCommands::Seek{position} => spotify.seek(position).await.map(Response::from),
+        SpotifyCommands::Restart => spotify.restart().await.map(Response::from),
+        SpotifyCommands::Status => spotify.status().await.map(Response::from),
+        SpotifyCommands::Device{command} => {
+            match command {
+                DeviceCommands::Connect{name} => spotify.device_connect(name).await.map(Response::from),
+                DeviceCommands::List => spotify.device_list().await.map(Response::from),
+                DeviceCommands::Status => spotify.device_status().await.map(Response::from),
             }
         }
+        SpotifyCommands::Library{command} => {
+            match command {
+                LibraryCommands::Like => spotify.like().await.map(Response::from),
+                LibraryCommands::Unlike => spotify.unlike().await.map(Response::from),
+                LibraryCommands::Tracks{limit} => spotify.saved_tracks(limit).await.map(Response::from),
+                LibraryCommands::Albums{limit} => spotify.saved_albums(limit).await.map(Response::from),
+                LibraryCommands::Shows{limit} => spotify.saved_shows(limit).await.map(Response::from),
+                LibraryCommands::Play => spotify.play_liked().await.map(Response::from),
+            }
+        }
+        SpotifyCommands::Playlist{command} => {
+            match command {
+                PlaylistCommands::Create{name, public} => spotify.playlist_create(name, public).await.map(Response::from),
+                PlaylistCommands::Add{playlist, query, url, _type} => spotify.playlist_add(&playlist, query, _type, url).await.map(Response::from),
+                PlaylistCommands::Remove{playlist, position} => spotify.playlist_remove(&playlist, position).await.map(Response::from),
+                PlaylistCommands::Move{playlist, from, to} => spotify.playlist_move(&playlist, from, to).await.map(Response::from),
+                PlaylistCommands::Rename{playlist, name} => spotify.playlist_rename(&playlist, name).await.map(Response::from),
+                PlaylistCommands::List{limit} => spotify.playlists(limit).await.map(Response::from),
+                PlaylistCommands::Show{playlist, limit} => spotify.playlist_show(&playlist, limit).await.map(Response::from),
+            }
+        }
+        SpotifyCommands::Set{command} => {
+            match command {
+                SetCommands::Volume{level} => spotify.set_volume(level as u8).await.map(Response::from),
+                SetCommands::Shuffle{state} => spotify.set_shuffle(state).await.map(Response::from),
+                SetCommands::Repeat{state} => spotify.set_repeat(state).await.map(Response::from),
+            }
+        }
+        // without progress on stderr, which would be drawn over the tui
+        SpotifyCommands::Tui => return tui::run(&fishify()).await,
+    }?;
 
-        return result.into();
-    }
-
-    if spotify.show {
-        println!("{}", spotify.response.join("\n"));
-    }
-
-    Ok(())
+    print_response(&response, cli.output)
 }
@@ -0,0 +1,340 @@
+use fishify_lib::{
+    spotify::Fishify,
+    response::{ Item, PlaybackStatus, QueueListing, DeviceInfo, },
+    clock::{ duration_clock_format, Position, },
+};
+
+use std::{
+    fmt,
+    io::{ self, Stdout, },
+    time::{ Duration, Instant, },
+};
+use anyhow::Result;
+use crossterm::{
+    execute,
+    event::{ self, Event, KeyCode, KeyEvent, KeyEventKind, },
+    terminal::{ enable_raw_mode, disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, },
+};
+use ratatui::{
+    Frame, Terminal,
+    backend::{ Backend, CrosstermBackend, },
+    layout::{ Constraint, Direction, Layout, Rect, },
+    style::{ Color, Modifier, Style, },
+    widgets::{ Block, Borders, Gauge, List, ListItem, ListState, Paragraph, },
+};
+use rspotify::model::enums::misc::RepeatState;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+const VOLUME_STEP: u32 = 5;
+
+fn seek_step() -> time::Duration {
+    time::Duration::seconds(10)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Queue,
+    Search,
+    Devices,
+}
+
+impl Pane {
+    fn next(self) -> Self {
+        match self {
+            Self::Queue => Self::Search,
+            Self::Search => Self::Devices,
+            Self::Devices => Self::Queue,
+        }
+    }
+}
+
+struct App {
+    status: Option<PlaybackStatus>,
+    queue: Option<QueueListing>,
+    devices: Vec<DeviceInfo>,
+    results: Vec<Item>,
+    query: String,
+    // whether keys go to the search box
+    editing: bool,
+    pane: Pane,
+    queue_state: ListState,
+    search_state: ListState,
+    device_state: ListState,
+    message: String,
+    quit: bool,
+}
+
+impl App {
+    fn new() -> Self {
+        return Self {
+            status: None,
+            queue: None,
+            devices: vec![],
+            results: vec![],
+            query: String::new(),
+            editing: false,
+            pane: Pane::Queue,
+            queue_state: ListState::default(),
+            search_state: ListState::default(),
+            device_state: ListState::default(),
+            message: "Press ? for help".to_string(),
+            quit: false,
+        };
+    }
+
+    fn list_state(&mut self) -> (&mut ListState, usize) {
+        match self.pane {
+            Pane::Queue => (&mut self.queue_state, self.queue.as_ref().map_or(0, |x| x.queue.len())),
+            Pane::Search => (&mut self.search_state, self.results.len()),
+            Pane::Devices => (&mut self.device_state, self.devices.len()),
+        }
+    }
+
+    fn select(&mut self, offset: isize) {
+        let (state, len) = self.list_state();
+        if len == 0 {
+            state.select(None);
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some((current + offset).rem_euclid(len as isize) as usize));
+    }
+
+    async fn refresh(&mut self, spotify: &Fishify<'_>) {
+        // no playback is normal, e.g. before connecting to a device
+        self.status = spotify.status().await.ok();
+        self.queue = spotify.queue_list().await.ok();
+    }
+
+    fn report<T: ToString, E: fmt::Display>(&mut self, result: Result<T, E>) {
+        self.message = match result {
+            Ok(response) => response.to_string(),
+            Err(err) => format!("Error: {err}"),
+        };
+    }
+
+    async fn handle_key(&mut self, spotify: &Fishify<'_>, key: KeyEvent) {
+        if self.editing {
+            match key.code {
+                KeyCode::Enter => {
+                    self.editing = false;
+                    match spotify.search(self.query.clone(), None, Some(20)).await {
+                        Ok(results) => {
+                            self.message = format!("{} results", results.items.len());
+                            self.results = results.items;
+                            self.search_state.select(if self.results.is_empty() { None } else { Some(0) });
+                        },
+                        Err(err) => self.message = format!("Error: {err}"),
+                    }
+                },
+                KeyCode::Esc => self.editing = false,
+                KeyCode::Backspace => { self.query.pop(); },
+                KeyCode::Char(c) => self.query.push(c),
+                _ => (),
+            }
+            return;
+        }
+
+        let volume = self.status.as_ref().and_then(|x| x.volume).unwrap_or(0);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('?') => self.message = "space play/pause, n next, p previous, left/right seek, +/- volume, s shuffle, r repeat, / search, tab switch pane, enter play, a queue, q quit".to_string(),
+            KeyCode::Char(' ') => {
+                let result = if self.status.as_ref().is_some_and(|x| x.is_playing) {
+                    spotify.pause().await
+                } else {
+                    spotify.play(None, None, false, false).await
+                };
+                self.report(result);
+            },
+            KeyCode::Char('n') => self.report(spotify.skip(1).await),
+            KeyCode::Char('p') => self.report(spotify.previous().await),
+            KeyCode::Left => self.report(spotify.seek(Position::Relative(-seek_step())).await),
+            KeyCode::Right => self.report(spotify.seek(Position::Relative(seek_step())).await),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.report(spotify.set_volume((volume + VOLUME_STEP).min(100) as u8).await),
+            KeyCode::Char('-') => self.report(spotify.set_volume(volume.saturating_sub(VOLUME_STEP) as u8).await),
+            KeyCode::Char('s') => {
+                let shuffle = self.status.as_ref().is_some_and(|x| x.shuffle);
+                self.report(spotify.set_shuffle(!shuffle).await);
+            },
+            KeyCode::Char('r') => {
+                let repeat = match self.status.as_ref().map(|x| x.repeat) {
+                    Some(RepeatState::Off) | None => RepeatState::Context,
+                    Some(RepeatState::Context) => RepeatState::Track,
+                    Some(RepeatState::Track) => RepeatState::Off,
+                };
+                self.report(spotify.set_repeat(repeat).await);
+            },
+            KeyCode::Char('/') => {
+                self.pane = Pane::Search;
+                self.editing = true;
+            },
+            KeyCode::Tab => {
+                self.pane = self.pane.next();
+                if self.pane == Pane::Devices {
+                    match spotify.device_list().await {
+                        Ok(list) => self.devices = list.devices,
+                        Err(err) => self.message = format!("Error: {err}"),
+                    }
+                }
+            },
+            KeyCode::Down | KeyCode::Char('j') => self.select(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+            KeyCode::Enter | KeyCode::Char('a') => {
+                let queue = key.code != KeyCode::Enter;
+                match self.pane {
+                    Pane::Search => {
+                        let uri = self.search_state.selected().and_then(|i| self.results.get(i)).and_then(|x| x.uri.clone());
+                        if let Some(uri) = uri {
+                            self.report(spotify.play_uri(&uri, queue).await);
+                        }
+                    },
+                    Pane::Devices => {
+                        let name = self.device_state.selected().and_then(|i| self.devices.get(i)).map(|x| x.name.clone());
+                        if name.is_some() {
+                            self.report(spotify.device_connect(name).await);
+                        }
+                    },
+                    Pane::Queue => (),
+                }
+            },
+            _ => return,
+        }
+
+        self.refresh(spotify).await;
+    }
+}
+
+fn pane_block(title: &str, focused: bool) -> Block {
+    let style = if focused {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    };
+    Block::default().title(title.to_string()).borders(Borders::ALL).border_style(style)
+}
+
+fn item_list<'a>(items: impl Iterator<Item = String>, block: Block<'a>) -> List<'a> {
+    List::new(items.map(ListItem::new).collect::<Vec<ListItem>>())
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+}
+
+fn draw_now_playing<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let block = Block::default().title("Now playing").borders(Borders::ALL);
+
+    let status = match &app.status {
+        Some(status) => status,
+        None => {
+            f.render_widget(Paragraph::new("Nothing playing").block(block), area);
+            return;
+        },
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Length(1)])
+        .split(block.inner(area));
+    f.render_widget(block, area);
+
+    let state = if status.is_playing { "Playing" } else { "Paused" };
+    let title = status.item.as_ref().map_or("Nothing".to_string(), |x| x.to_string());
+    let settings = format!(
+        "Volume: {}%  Shuffle: {}  Repeat: {:?}",
+        status.volume.map_or("-".to_string(), |x| x.to_string()),
+        if status.shuffle { "On" } else { "Off" },
+        status.repeat,
+    );
+    f.render_widget(Paragraph::new(format!("{state}: {title}\n{settings}")), chunks[0]);
+
+    let duration = status.item.as_ref().and_then(|x| x.duration);
+    if let (Some(progress), Some(duration)) = (status.progress, duration) {
+        let ratio = progress.num_milliseconds() as f64 / duration.num_milliseconds().max(1) as f64;
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Green))
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(format!("{} / {}", duration_clock_format(progress), duration_clock_format(duration)));
+        f.render_widget(gauge, chunks[1]);
+    }
+}
+
+fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(5), Constraint::Min(5), Constraint::Length(1)])
+        .split(f.size());
+
+    draw_now_playing(f, app, rows[0]);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[1]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(3), Constraint::Length(6)])
+        .split(columns[1]);
+
+    let queue = app.queue.as_ref().map_or(vec![], |x| x.queue.iter().map(|x| x.to_string()).collect());
+    let queue = item_list(queue.into_iter(), pane_block("Queue", app.pane == Pane::Queue));
+    f.render_stateful_widget(queue, columns[0], &mut app.queue_state);
+
+    let input_title = if app.editing { "Search (enter to search, esc to cancel)" } else { "Search (/)" };
+    let input = Paragraph::new(app.query.clone()).block(pane_block(input_title, app.editing));
+    f.render_widget(input, right[0]);
+
+    let results = item_list(app.results.iter().map(|x| x.to_string()), pane_block("Results", app.pane == Pane::Search));
+    f.render_stateful_widget(results, right[1], &mut app.search_state);
+
+    let devices = app.devices.iter().map(|x| {
+        let active = if x.is_active { "* " } else { "  " };
+        format!("{active}{} ({:?})", x.name, x._type)
+    });
+    let devices = item_list(devices, pane_block("Devices", app.pane == Pane::Devices));
+    f.render_stateful_widget(devices, right[2], &mut app.device_state);
+
+    f.render_widget(Paragraph::new(app.message.clone()), rows[2]);
+}
+
+async fn event_loop(terminal: &mut Terminal<CrosstermBackend<Stdout>>, spotify: &Fishify<'_>) -> Result<()> {
+    let mut app = App::new();
+    app.refresh(spotify).await;
+    let mut last_refresh = Instant::now();
+
+    while !app.quit {
+        terminal.draw(|f| draw(f, &mut app))?;
+
+        let timeout = REFRESH_INTERVAL.saturating_sub(last_refresh.elapsed());
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                // windows also reports releases
+                if key.kind == KeyEventKind::Press {
+                    app.handle_key(spotify, key).await;
+                }
+            }
+        }
+
+        if last_refresh.elapsed() >= REFRESH_INTERVAL {
+            app.refresh(spotify).await;
+            last_refresh = Instant::now();
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn run(spotify: &Fishify<'_>) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    // restore the terminal even if the loop fails
+    let result = event_loop(&mut terminal, spotify).await;
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
@@ -0,0 +1,511 @@
+use fishify_lib::{
+    backend::SpotifyBackend,
+    error::FishifyError,
+    fake::FakePlayer,
+};
+
+use std::{
+    collections::{ HashMap, HashSet, VecDeque, },
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{ Arc, Mutex, },
+};
+
+use anyhow::Result;
+use axum::{
+    Router, Json,
+    extract::{ Path, Query, State, },
+    http::{ Request, StatusCode, header::RETRY_AFTER, },
+    middleware::{ self, Next, },
+    response::{ IntoResponse, Response, },
+    routing::{ get, put, post, },
+};
+use clap::Parser;
+use serde_json::{ json, Value, };
+use rspotify::{
+    Token,
+    model::{
+        idtypes::{ TrackId, AlbumId, PlaylistId, ArtistId, ShowId, EpisodeId, },
+        enums::types::{ SearchType, DeviceType, },
+        search::SearchResult,
+    },
+    prelude::{ PlayContextId, PlayableId, },
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "fishify-fake-api")]
+#[command(about = "A fake spotify web api for testing fishify", long_about = None)]
+struct Args {
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 8899)]
+    port: u16,
+    /// Write a token that never needs refreshing to this token cache path
+    #[arg(short, long)]
+    token_cache: Option<PathBuf>,
+}
+
+// an error to return instead of handling a request
+#[derive(Debug, Clone)]
+struct Failure {
+    status: StatusCode,
+    retry_after: Option<u64>,
+    // only fail requests to this path, any path if none
+    path: Option<String>,
+}
+
+#[derive(Clone)]
+struct AppState {
+    player: Arc<FakePlayer>,
+    failures: Arc<Mutex<VecDeque<Failure>>>,
+}
+
+// errors look like the web api's, {"error": {"status": 404, "message": "..."}}
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = json!({ "error": { "status": self.0.as_u16(), "message": self.1 } });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        let message = err.to_string();
+        // the fake player only fails on missing things, except for bad requests
+        let status = match err.downcast_ref::<FishifyError>() {
+            Some(FishifyError::NoActiveDevice | FishifyError::DeviceNotFound(_)) => StatusCode::NOT_FOUND,
+            _ if message.starts_with("Not found") => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self(status, message)
+    }
+}
+
+impl From<rspotify::model::idtypes::IdError> for ApiError {
+    fn from(err: rspotify::model::idtypes::IdError) -> Self {
+        Self(StatusCode::BAD_REQUEST, format!("Invalid id: {err:?}"))
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+type Params = Query<HashMap<String, String>>;
+
+fn bad_request(message: &str) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, message.to_string())
+}
+
+fn param<'a>(params: &'a Params, name: &str) -> ApiResult<&'a str> {
+    params.get(name).map(|x| x.as_str()).ok_or(bad_request(&format!("Missing {name}")))
+}
+
+// seed the player with something to search for and play
+fn seed(player: &FakePlayer) {
+    let fish = player.add_artist("The Fish");
+    let crab = player.add_artist("Crab Rave Orchestra");
+
+    let tracks = [
+        player.add_track("Swimming", &fish, 185_000),
+        player.add_track("Deep Blue", &fish, 242_000),
+        player.add_track("Gills", &fish, 131_000),
+    ];
+    let rave = player.add_track("Crab Rave", &crab, 193_000);
+    let refs: Vec<&str> = tracks.iter().map(|x| x.as_str()).collect();
+
+    let album = player.add_album("Underwater", &fish, &refs);
+    player.save(&tracks[1]);
+    player.save(&rave);
+    player.save(&album);
+    player.add_playlist("Aquarium", &[&tracks[0], &rave, &tracks[2]]);
+    player.add_show("Fish Talk", &[("Episode 1: Scales", 1_800_000), ("Episode 2: Fins", 2_100_000)]);
+
+    player.add_device("Laptop", DeviceType::Computer);
+    player.add_device("Kitchen Speaker", DeviceType::Speaker);
+}
+
+fn write_token(path: &PathBuf) -> Result<()> {
+    let token = Token {
+        access_token: "fake".to_string(),
+        expires_in: chrono::Duration::days(365),
+        expires_at: Some(chrono::Utc::now() + chrono::Duration::days(365)),
+        refresh_token: None,
+        scopes: HashSet::new(),
+    };
+    token.write_cache(path)?;
+    Ok(())
+}
+
+async fn inject<B>(State(state): State<AppState>, req: Request<B>, next: Next<B>) -> Response {
+    let path = req.uri().path().to_string();
+    if !path.starts_with("/_fake") {
+        let failure = {
+            let mut failures = state.failures.lock().unwrap();
+            let index = failures.iter().position(|x| x.path.as_ref().map_or(true, |p| *p == path));
+            index.and_then(|i| failures.remove(i))
+        };
+
+        if let Some(failure) = failure {
+            let mut response = ApiError(failure.status, "Injected failure".to_string()).into_response();
+            if let Some(retry_after) = failure.retry_after {
+                response.headers_mut().insert(RETRY_AFTER, retry_after.into());
+            }
+            return response;
+        }
+    }
+    next.run(req).await
+}
+
+async fn fail(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    let status = param(&params, "status")?.parse::<u16>().ok()
+        .and_then(|x| StatusCode::from_u16(x).ok())
+        .ok_or(bad_request("Invalid status"))?;
+    let count: usize = params.get("count").and_then(|x| x.parse().ok()).unwrap_or(1);
+    let failure = Failure {
+        status,
+        retry_after: params.get("retry_after").and_then(|x| x.parse().ok()),
+        path: params.get("path").cloned(),
+    };
+
+    let mut failures = state.failures.lock().unwrap();
+    for _ in 0..count {
+        failures.push_back(failure.clone());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn clear(State(state): State<AppState>) -> StatusCode {
+    state.failures.lock().unwrap().clear();
+    StatusCode::NO_CONTENT
+}
+
+async fn search(State(state): State<AppState>, params: Params) -> ApiResult<Json<Value>> {
+    let _type = match param(&params, "type")? {
+        "track" => SearchType::Track,
+        "album" => SearchType::Album,
+        "playlist" => SearchType::Playlist,
+        "artist" => SearchType::Artist,
+        "show" => SearchType::Show,
+        "episode" => SearchType::Episode,
+        _ => return Err(bad_request("Invalid type")),
+    };
+    let limit = params.get("limit").and_then(|x| x.parse().ok());
+    let offset = params.get("offset").and_then(|x| x.parse().ok());
+
+    let result = state.player.search(param(&params, "q")?, _type, limit, offset).await?;
+    let body = match result {
+        SearchResult::Tracks(page) => json!({ "tracks": page }),
+        SearchResult::Albums(page) => json!({ "albums": page }),
+        SearchResult::Playlists(page) => json!({ "playlists": page }),
+        SearchResult::Artists(page) => json!({ "artists": page }),
+        SearchResult::Shows(page) => json!({ "shows": page }),
+        SearchResult::Episodes(page) => json!({ "episodes": page }),
+    };
+    Ok(Json(body))
+}
+
+async fn playback(State(state): State<AppState>) -> ApiResult<Response> {
+    match state.player.current_playback().await? {
+        Some(playback) => Ok(Json(playback).into_response()),
+        None => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+async fn transfer(State(state): State<AppState>, Json(body): Json<Value>) -> ApiResult<StatusCode> {
+    let device_id = body["device_ids"][0].as_str().ok_or(bad_request("Missing device_ids"))?;
+    state.player.transfer(device_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn queue(State(state): State<AppState>) -> ApiResult<Json<Value>> {
+    let queue = state.player.current_queue().await?;
+    Ok(Json(json!(queue)))
+}
+
+async fn add_to_queue(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    let uri = param(&params, "uri")?;
+    let id = match uri.split(':').nth(1) {
+        Some("episode") => PlayableId::from(EpisodeId::from_uri(uri)?),
+        _ => PlayableId::from(TrackId::from_uri(uri)?),
+    };
+    state.player.add_to_queue(id, params.get("device_id").map(|x| x.as_str())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn devices(State(state): State<AppState>) -> ApiResult<Json<Value>> {
+    let devices = state.player.devices().await?;
+    Ok(Json(json!({ "devices": devices })))
+}
+
+// start a context, start a list of uris, or resume when the body has neither
+async fn play(State(state): State<AppState>, params: Params, body: Option<Json<Value>>) -> ApiResult<StatusCode> {
+    let device_id = params.get("device_id").map(|x| x.as_str());
+    let body = body.map(|Json(x)| x).unwrap_or(Value::Null);
+
+    if let Some(uri) = body["context_uri"].as_str() {
+        let id = match uri.split(':').nth(1) {
+            Some("album") => PlayContextId::from(AlbumId::from_uri(uri)?),
+            Some("playlist") => PlayContextId::from(PlaylistId::from_uri(uri)?),
+            Some("artist") => PlayContextId::from(ArtistId::from_uri(uri)?),
+            Some("show") => PlayContextId::from(ShowId::from_uri(uri)?),
+            _ => return Err(bad_request("Invalid context_uri")),
+        };
+        state.player.start_context(id, device_id).await?;
+    } else if let Some(uris) = body["uris"].as_array() {
+        let mut ids = vec![];
+        for uri in uris.iter().filter_map(|x| x.as_str()) {
+            ids.push(match uri.split(':').nth(1) {
+                Some("episode") => PlayableId::from(EpisodeId::from_uri(uri)?),
+                _ => PlayableId::from(TrackId::from_uri(uri)?),
+            });
+        }
+        state.player.start_uris(ids, device_id).await?;
+    } else {
+        state.player.resume(device_id).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn pause(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    state.player.pause(params.get("device_id").map(|x| x.as_str())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn next(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    state.player.next(params.get("device_id").map(|x| x.as_str())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn previous(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    state.player.previous(params.get("device_id").map(|x| x.as_str())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn seek(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    let position_ms = param(&params, "position_ms")?.parse().map_err(|_| bad_request("Invalid position_ms"))?;
+    state.player.seek(time::Duration::milliseconds(position_ms), params.get("device_id").map(|x| x.as_str())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn volume(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    let level = param(&params, "volume_percent")?.parse().map_err(|_| bad_request("Invalid volume_percent"))?;
+    state.player.volume(level, params.get("device_id").map(|x| x.as_str())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn shuffle(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    let shuffle = param(&params, "state")?.parse().map_err(|_| bad_request("Invalid state"))?;
+    state.player.shuffle(shuffle, params.get("device_id").map(|x| x.as_str())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn repeat(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    let repeat = serde_json::from_value(json!(param(&params, "state")?)).map_err(|_| bad_request("Invalid state"))?;
+    state.player.repeat(repeat, params.get("device_id").map(|x| x.as_str())).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn track_ids(params: &Params) -> ApiResult<Vec<TrackId>> {
+    let ids = param(params, "ids")?.split(',').map(TrackId::from_id).collect::<std::result::Result<Vec<TrackId>, _>>()?;
+    Ok(ids)
+}
+
+async fn save_tracks(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    state.player.save_tracks(track_ids(&params)?).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn unsave_tracks(State(state): State<AppState>, params: Params) -> ApiResult<StatusCode> {
+    state.player.unsave_tracks(track_ids(&params)?).await?;
+    Ok(StatusCode::OK)
+}
+
+fn page_params(params: &Params) -> (Option<u32>, Option<u32>) {
+    (params.get("limit").and_then(|x| x.parse().ok()), params.get("offset").and_then(|x| x.parse().ok()))
+}
+
+async fn saved_tracks(State(state): State<AppState>, params: Params) -> ApiResult<Json<Value>> {
+    let (limit, offset) = page_params(&params);
+    Ok(Json(json!(state.player.saved_tracks(limit, offset).await?)))
+}
+
+async fn saved_albums(State(state): State<AppState>, params: Params) -> ApiResult<Json<Value>> {
+    let (limit, offset) = page_params(&params);
+    Ok(Json(json!(state.player.saved_albums(limit, offset).await?)))
+}
+
+async fn saved_shows(State(state): State<AppState>, params: Params) -> ApiResult<Json<Value>> {
+    let (limit, offset) = page_params(&params);
+    Ok(Json(json!(state.player.saved_shows(limit, offset).await?)))
+}
+
+async fn track(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<Value>> {
+    Ok(Json(json!(state.player.track(TrackId::from_id(&id)?).await?)))
+}
+
+async fn album(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<Value>> {
+    Ok(Json(json!(state.player.album(AlbumId::from_id(&id)?).await?)))
+}
+
+async fn album_tracks(State(state): State<AppState>, Path(id): Path<String>, params: Params) -> ApiResult<Json<Value>> {
+    let (limit, offset) = page_params(&params);
+    Ok(Json(json!(state.player.album_tracks(AlbumId::from_id(&id)?, limit, offset).await?)))
+}
+
+async fn playlist(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<Value>> {
+    Ok(Json(json!(state.player.playlist(PlaylistId::from_id(&id)?).await?)))
+}
+
+async fn artist(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<Value>> {
+    Ok(Json(json!(state.player.artist(ArtistId::from_id(&id)?).await?)))
+}
+
+async fn show(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<Value>> {
+    Ok(Json(json!(state.player.show(ShowId::from_id(&id)?).await?)))
+}
+
+async fn show_episodes(State(state): State<AppState>, Path(id): Path<String>, params: Params) -> ApiResult<Json<Value>> {
+    let (limit, offset) = page_params(&params);
+    Ok(Json(json!(state.player.show_episodes(ShowId::from_id(&id)?, limit, offset).await?)))
+}
+
+async fn episode(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<Value>> {
+    Ok(Json(json!(state.player.episode(EpisodeId::from_id(&id)?).await?)))
+}
+
+fn playable_id(uri: &str) -> ApiResult<PlayableId<'_>> {
+    match uri.split(':').nth(1) {
+        Some("episode") => Ok(PlayableId::from(EpisodeId::from_uri(uri)?)),
+        _ => Ok(PlayableId::from(TrackId::from_uri(uri)?)),
+    }
+}
+
+// the fake player has a single user
+async fn me() -> Json<Value> {
+    Json(json!({
+        "id": "fishify",
+        "display_name": "fishify",
+        "href": "https://api.spotify.com/v1/users/fishify",
+        "external_urls": { "spotify": "https://open.spotify.com/user/fishify" },
+    }))
+}
+
+async fn my_playlists(State(state): State<AppState>, params: Params) -> ApiResult<Json<Value>> {
+    let (limit, offset) = page_params(&params);
+    Ok(Json(json!(state.player.my_playlists(limit, offset).await?)))
+}
+
+async fn playlist_create(State(state): State<AppState>, Json(body): Json<Value>) -> ApiResult<(StatusCode, Json<Value>)> {
+    let name = body["name"].as_str().ok_or(bad_request("Missing name"))?;
+    let public = body["public"].as_bool().unwrap_or(true);
+    let playlist = state.player.playlist_create(name, public).await?;
+    Ok((StatusCode::CREATED, Json(json!(playlist))))
+}
+
+async fn playlist_rename(State(state): State<AppState>, Path(id): Path<String>, Json(body): Json<Value>) -> ApiResult<StatusCode> {
+    let name = body["name"].as_str().ok_or(bad_request("Missing name"))?;
+    state.player.playlist_rename(PlaylistId::from_id(&id)?, name).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn playlist_items(State(state): State<AppState>, Path(id): Path<String>, params: Params) -> ApiResult<Json<Value>> {
+    let (limit, offset) = page_params(&params);
+    Ok(Json(json!(state.player.playlist_items(PlaylistId::from_id(&id)?, limit, offset).await?)))
+}
+
+// the fake player doesn't track snapshots, so every edit returns the same one
+fn snapshot() -> Json<Value> {
+    Json(json!({ "snapshot_id": "fake" }))
+}
+
+async fn playlist_add(State(state): State<AppState>, Path(id): Path<String>, Json(body): Json<Value>) -> ApiResult<(StatusCode, Json<Value>)> {
+    let uris = body["uris"].as_array().ok_or(bad_request("Missing uris"))?;
+    let ids = uris.iter().filter_map(|x| x.as_str()).map(playable_id).collect::<ApiResult<Vec<PlayableId>>>()?;
+    state.player.playlist_add(PlaylistId::from_id(&id)?, ids).await?;
+    Ok((StatusCode::CREATED, snapshot()))
+}
+
+async fn playlist_remove(State(state): State<AppState>, Path(id): Path<String>, Json(body): Json<Value>) -> ApiResult<Json<Value>> {
+    let tracks = body["tracks"].as_array().ok_or(bad_request("Missing tracks"))?;
+    let snapshot_id = body["snapshot_id"].as_str().unwrap_or_default();
+    let mut removals = vec![];
+    for track in tracks {
+        let uri = track["uri"].as_str().ok_or(bad_request("Missing uri"))?;
+        let positions = track["positions"].as_array().ok_or(bad_request("Missing positions"))?;
+        for position in positions {
+            removals.push((uri, position.as_u64().ok_or(bad_request("Invalid position"))? as u32));
+        }
+    }
+    // positions are from before the removal, so later ones go first
+    removals.sort_by_key(|(_, position)| std::cmp::Reverse(*position));
+    for (uri, position) in removals {
+        state.player.playlist_remove(PlaylistId::from_id(&id)?, playable_id(uri)?, position, snapshot_id).await?;
+    }
+    Ok(snapshot())
+}
+
+async fn playlist_reorder(State(state): State<AppState>, Path(id): Path<String>, Json(body): Json<Value>) -> ApiResult<Json<Value>> {
+    let range_start = body["range_start"].as_u64().ok_or(bad_request("Missing range_start"))?;
+    let insert_before = body["insert_before"].as_u64().ok_or(bad_request("Missing insert_before"))?;
+    state.player.playlist_reorder(PlaylistId::from_id(&id)?, range_start as u32, insert_before as u32).await?;
+    Ok(snapshot())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    if let Some(path) = &args.token_cache {
+        write_token(path)?;
+    }
+
+    let player = FakePlayer::new();
+    seed(&player);
+
+    let state = AppState {
+        player: Arc::new(player),
+        failures: Arc::new(Mutex::new(VecDeque::new())),
+    };
+
+    let app = Router::new()
+        .route("/v1/search", get(search))
+        .route("/v1/me/player", get(playback).put(transfer))
+        .route("/v1/me/player/queue", get(queue).post(add_to_queue))
+        .route("/v1/me/player/devices", get(devices))
+        .route("/v1/me/player/play", put(play))
+        .route("/v1/me/player/pause", put(pause))
+        .route("/v1/me/player/next", post(next))
+        .route("/v1/me/player/previous", post(previous))
+        .route("/v1/me/player/seek", put(seek))
+        .route("/v1/me/player/volume", put(volume))
+        .route("/v1/me/player/shuffle", put(shuffle))
+        .route("/v1/me/player/repeat", put(repeat))
+        .route("/v1/tracks/:id", get(track))
+        .route("/v1/albums/:id", get(album))
+        .route("/v1/albums/:id/tracks", get(album_tracks))
+        .route("/v1/playlists/:id", get(playlist).put(playlist_rename))
+        .route("/v1/playlists/:id/tracks", get(playlist_items).post(playlist_add).delete(playlist_remove).put(playlist_reorder))
+        .route("/v1/artists/:id", get(artist))
+        .route("/v1/shows/:id", get(show))
+        .route("/v1/shows/:id/episodes", get(show_episodes))
+        .route("/v1/episodes/:id", get(episode))
+        // rspotify adds a trailing slash to some of these
+        .route("/v1/me/tracks", get(saved_tracks).put(save_tracks).delete(unsave_tracks))
+        .route("/v1/me/tracks/", get(saved_tracks).put(save_tracks).delete(unsave_tracks))
+        .route("/v1/me/albums", get(saved_albums))
+        .route("/v1/me/shows", get(saved_shows))
+        .route("/v1/me", get(me))
+        .route("/v1/me/playlists", get(my_playlists))
+        .route("/v1/users/:id/playlists", post(playlist_create))
+        .route("/_fake/fail", post(fail))
+        .route("/_fake/clear", post(clear))
+        .layer(middleware::from_fn_with_state(state.clone(), inject))
+        .with_state(state);
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], args.port));
+    println!("Listening on http://{addr}/v1/");
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,350 @@
+use crate::error::FishifyError;
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    time::{ Duration, SystemTime, UNIX_EPOCH, },
+};
+
+use anyhow::{ anyhow, Result, };
+use serde::{ Deserialize, Serialize, };
+use rspotify::{
+    AuthCodeSpotify, AuthCodePkceSpotify, OAuth,
+    clients::{ BaseClient, OAuthClient, },
+};
+use tokio::{
+    io::{ AsyncReadExt, AsyncWriteExt, },
+    net::{ TcpListener, TcpStream, },
+    sync::mpsc,
+};
+
+// how long a login url is reused for, spotify forgets about it eventually anyway
+const PENDING_TIMEOUT: u64 = 24 * 60 * 60;
+// how long a connection to the redirect uri gets to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A login that was started but not finished, kept on disk so the url spotify redirects to can be
+// pasted in later, even from another run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pending {
+    pub state: String,
+    pub url: String,
+    // unix seconds
+    pub started: u64,
+    // pkce's code verifier, the redirect's code can't be exchanged without it
+    #[serde(default)]
+    pub verifier: Option<String>,
+}
+
+impl Pending {
+    // None if there's no login waiting, or it's too old to finish
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let pending: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(Some(pending).filter(|x| now().saturating_sub(x.started) < PENDING_TIMEOUT))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn clear(path: &Path) -> Result<()> {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or_default()
+}
+
+// What logging in needs from the authorization code clients, with and without pkce
+pub trait LoginClient: OAuthClient + Send + Sync {
+    fn authorize_url(&mut self) -> Result<String>;
+    fn oauth_mut(&mut self) -> &mut OAuth;
+
+    fn verifier(&self) -> Option<String> {
+        None
+    }
+
+    fn set_verifier(&mut self, _verifier: Option<String>) {}
+}
+
+impl LoginClient for AuthCodeSpotify {
+    fn authorize_url(&mut self) -> Result<String> {
+        Ok(self.get_authorize_url(false)?)
+    }
+
+    fn oauth_mut(&mut self) -> &mut OAuth {
+        &mut self.oauth
+    }
+}
+
+impl LoginClient for AuthCodePkceSpotify {
+    // makes a new verifier, which has to be kept until the login is finished
+    fn authorize_url(&mut self) -> Result<String> {
+        Ok(self.get_authorize_url(None)?)
+    }
+
+    fn oauth_mut(&mut self) -> &mut OAuth {
+        &mut self.oauth
+    }
+
+    fn verifier(&self) -> Option<String> {
+        self.verifier.clone()
+    }
+
+    fn set_verifier(&mut self, verifier: Option<String>) {
+        self.verifier = verifier;
+    }
+}
+
+// Uses the cached token if there is one, refreshing it if it expired. false if there's no usable token
+pub async fn cached_login(spotify: &impl BaseClient) -> Result<bool> {
+    let token = match spotify.read_token_cache(true).await {
+        Ok(Some(token)) => token,
+        _ => return Ok(false),
+    };
+    let expired = token.is_expired();
+    *spotify.get_token().lock().await.unwrap() = Some(token);
+    if expired && spotify.refresh_token().await.is_err() {
+        *spotify.get_token().lock().await.unwrap() = None;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+// The url to authorize fishify at. An unfinished login's url is reused, so a redirect from an
+// earlier run can still finish it
+pub fn start_login(spotify: &mut impl LoginClient, pending_path: &Path) -> Result<String> {
+    if let Some(pending) = Pending::load(pending_path)? {
+        spotify.oauth_mut().state = pending.state;
+        spotify.set_verifier(pending.verifier);
+        return Ok(pending.url);
+    }
+    let url = spotify.authorize_url()?;
+    Pending {
+        state: spotify.get_oauth().state.clone(),
+        url: url.clone(),
+        started: now(),
+        verifier: spotify.verifier(),
+    }.save(pending_path)?;
+    Ok(url)
+}
+
+// Finishes a login with the url spotify redirected to, for when the browser couldn't reach wait_for_login
+pub async fn finish_login(spotify: &mut impl LoginClient, pending_path: &Path, redirected: &str) -> Result<()> {
+    let pending = Pending::load(pending_path)?.ok_or(FishifyError::NoPendingLogin)?;
+    let path = redirected.split_once("://").map_or(redirected, |(_, rest)| rest);
+    let path = path.find('/').map_or("/", |start| &path[start..]);
+    let callback = match parse_callback(&format!("GET {path} HTTP/1.1")) {
+        Some(Ok(callback)) => callback,
+        Some(Err(error)) => return Err(anyhow!("Authorization failed: {error}")),
+        None => return Err(FishifyError::InvalidUrl(redirected.to_string()).into()),
+    };
+    if callback.state.as_deref() != Some(pending.state.as_str()) {
+        return Err(FishifyError::NoPendingLogin.into());
+    }
+    spotify.set_verifier(pending.verifier);
+    spotify.request_token(&callback.code).await?;
+    Pending::clear(pending_path)
+}
+
+// Serves the redirect uri until spotify redirects to it with the pending login's state. Only on this
+// machine, since the browser that's redirected is on it. From anywhere else, use finish_login
+pub async fn wait_for_login(spotify: &impl LoginClient, port: u16, pending_path: &Path) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    let mut callbacks = accept_callbacks(listener);
+    while let Some((callback, stream)) = callbacks.recv().await {
+        let callback = match callback {
+            Ok(callback) => callback,
+            Err(error) => {
+                respond(stream, false, &format!("Spotify said: {error}")).await?;
+                return Err(anyhow!("Authorization failed: {error}"));
+            },
+        };
+        if callback.state.as_deref() != Some(spotify.get_oauth().state.as_str()) {
+            respond(stream, false, "This is from an older login, open the latest url").await?;
+            continue;
+        }
+        match spotify.request_token(&callback.code).await {
+            Ok(()) => {
+                respond(stream, true, "").await?;
+                return Pending::clear(pending_path);
+            },
+            Err(err) => {
+                respond(stream, false, &err.to_string()).await?;
+                return Err(err.into());
+            },
+        }
+    }
+    Err(anyhow!("Stopped listening for spotify's redirect"))
+}
+
+// Logs in without reading stdin: the cached token, or else the url is printed and the redirect is
+// caught on `port`. If the browser can't reach it, the redirected url can be given to finish_login
+// later, which is what `fishify login --url` does
+pub async fn login(spotify: &mut impl LoginClient, port: u16, pending_path: &Path) -> Result<()> {
+    if cached_login(spotify).await? {
+        return Ok(());
+    }
+    let url = start_login(spotify, pending_path)?;
+    eprintln!("Open this url to authorize fishify:\n{url}");
+    eprintln!("Waiting for spotify to redirect to port {port}. If it can't reach this machine, copy the url it redirects to and finish with it later");
+    wait_for_login(spotify, port, pending_path).await
+}
+
+// What spotify redirects back with after someone authorizes fishify
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Callback {
+    pub code: String,
+    pub state: Option<String>,
+}
+
+// A redirect from spotify, or the error it redirected with, and the stream for answering once the
+// code has been exchanged
+pub type Redirect = (Result<Callback, String>, TcpStream);
+
+// Accepts connections until the receiver is dropped, reading each on its own task so a slow or idle
+// one can't hold up the rest. Only spotify's redirects are passed on, anything else, like a browser
+// asking for a favicon, is answered with a 404
+pub fn accept_callbacks(listener: TcpListener) -> mpsc::UnboundedReceiver<Redirect> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = sender.closed() => return,
+            };
+            let stream = match accepted {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    eprintln!("Error while accepting a connection on the redirect uri: {err}");
+                    continue;
+                },
+            };
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                match read_callback(stream).await {
+                    Ok(Some(redirect)) => {
+                        let _ = sender.send(redirect);
+                    },
+                    Ok(None) => (),
+                    Err(err) => eprintln!("Error while reading a request on the redirect uri: {err}"),
+                }
+            });
+        }
+    });
+    receiver
+}
+
+// None for anything that isn't a redirect from spotify, after answering it
+async fn read_callback(mut stream: TcpStream) -> Result<Option<Redirect>> {
+    let mut buf = vec![0; 4096];
+    let read = tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buf)).await??;
+    let request = String::from_utf8_lossy(&buf[..read]);
+
+    match parse_callback(&request) {
+        Some(callback) => Ok(Some((callback, stream))),
+        None => {
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n").await?;
+            Ok(None)
+        },
+    }
+}
+
+// a page for the browser that was redirected, so whoever authorized knows whether it worked
+pub async fn respond(mut stream: TcpStream, ok: bool, message: &str) -> Result<()> {
+    let (status, title) = match ok {
+        true => ("200 OK", "Fishify is authorized, you can close this tab"),
+        false => ("400 Bad Request", "Fishify couldn't be authorized"),
+    };
+    let body = format!("<!doctype html><html><body><h1>{title}</h1><p>{}</p></body></html>", escape_html(message));
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len(),
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+// messages can echo what was in the request, like spotify's error
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// None for requests that aren't a redirect from spotify, Err for a redirect with an error,
+// like when someone presses cancel
+fn parse_callback(request: &str) -> Option<Result<Callback, String>> {
+    let path = request.lines().next()?.split_whitespace().nth(1)?;
+    let (_, query) = path.split_once('?')?;
+    let params: HashMap<&str, &str> = query.split('&').filter_map(|x| x.split_once('=')).collect();
+
+    if let Some(error) = params.get("error") {
+        return Some(Err(error.to_string()));
+    }
+    let code = params.get("code")?;
+    Some(Ok(Callback {
+        code: code.to_string(),
+        state: params.get("state").map(|x| x.to_string()),
+    }))
+}
+
+// the port a redirect uri like http://localhost:8888/callback is on
+pub fn redirect_port(redirect_uri: &str) -> Option<u16> {
+    let (scheme, rest) = redirect_uri.split_once("://")?;
+    let host = rest.split('/').next()?;
+    match host.rsplit_once(':') {
+        Some((_, port)) => port.parse().ok(),
+        None if scheme == "https" => Some(443),
+        None => Some(80),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn idle_connection_doesnt_block_redirect() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut callbacks = accept_callbacks(listener);
+
+        // connects but never sends anything
+        let _idle = TcpStream::connect(addr).await.unwrap();
+        let mut browser = TcpStream::connect(addr).await.unwrap();
+        browser.write_all(b"GET /callback?code=abc&state=xyz HTTP/1.1\r\n\r\n").await.unwrap();
+
+        let (callback, _) = tokio::time::timeout(Duration::from_secs(1), callbacks.recv()).await.unwrap().unwrap();
+        assert_eq!(callback, Ok(Callback { code: "abc".to_string(), state: Some("xyz".to_string()) }));
+    }
+
+    #[test]
+    fn html_escaped() {
+        assert_eq!(escape_html("<script>alert('hi & bye')</script>"), "&lt;script&gt;alert(&#39;hi &amp; bye&#39;)&lt;/script&gt;");
+    }
+
+    #[test]
+    fn callback_error() {
+        assert_eq!(parse_callback("GET /callback?error=access_denied HTTP/1.1"), Some(Err("access_denied".to_string())));
+        assert_eq!(parse_callback("GET /favicon.ico HTTP/1.1"), None);
+    }
+}
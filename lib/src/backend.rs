@@ -0,0 +1,246 @@
+use crate::error::FishifyError;
+
+use anyhow::Result;
+use time::Duration;
+use async_trait::async_trait;
+use rspotify::{
+    AuthCodeSpotify, AuthCodePkceSpotify,
+    clients::{ OAuthClient, BaseClient, },
+    model::{
+        track::{ FullTrack, SavedTrack, SimplifiedTrack, },
+        album::{ FullAlbum, SavedAlbum, },
+        playlist::{ FullPlaylist, SimplifiedPlaylist, PlaylistItem, },
+        artist::FullArtist,
+        show::{ FullShow, FullEpisode, SimplifiedEpisode, Show, },
+        page::Page,
+        idtypes::{ TrackId, AlbumId, PlaylistId, ArtistId, ShowId, EpisodeId, },
+        context::{ CurrentPlaybackContext, CurrentUserQueue, },
+        device::Device,
+        enums::{
+            types::{ AdditionalType, SearchType, },
+            misc::{ RepeatState, },
+        },
+        search::SearchResult,
+        ItemPositions,
+    },
+    prelude::{ PlayContextId, PlayableId, },
+};
+
+// Everything Fishify needs from spotify. Implemented by the real rspotify clients and by
+// `fake::FakePlayer`, so Fishify can be used as a trait object and tested without credentials.
+// Web api errors are converted to `FishifyError` where there is a matching variant.
+#[async_trait]
+pub trait SpotifyBackend: Send + Sync {
+    async fn search(&self, query: &str, _type: SearchType, limit: Option<u32>, offset: Option<u32>) -> Result<SearchResult>;
+
+    async fn start_uris(&self, ids: Vec<PlayableId<'_>>, device_id: Option<&str>) -> Result<()>;
+    async fn start_context(&self, id: PlayContextId<'_>, device_id: Option<&str>) -> Result<()>;
+    async fn resume(&self, device_id: Option<&str>) -> Result<()>;
+    async fn pause(&self, device_id: Option<&str>) -> Result<()>;
+    async fn next(&self, device_id: Option<&str>) -> Result<()>;
+    async fn previous(&self, device_id: Option<&str>) -> Result<()>;
+    async fn seek(&self, position: Duration, device_id: Option<&str>) -> Result<()>;
+    async fn add_to_queue(&self, id: PlayableId<'_>, device_id: Option<&str>) -> Result<()>;
+    async fn volume(&self, level: u8, device_id: Option<&str>) -> Result<()>;
+    async fn shuffle(&self, state: bool, device_id: Option<&str>) -> Result<()>;
+    async fn repeat(&self, state: RepeatState, device_id: Option<&str>) -> Result<()>;
+
+    async fn devices(&self) -> Result<Vec<Device>>;
+    async fn transfer(&self, device_id: &str) -> Result<()>;
+    async fn current_playback(&self) -> Result<Option<CurrentPlaybackContext>>;
+    async fn current_queue(&self) -> Result<CurrentUserQueue>;
+
+    async fn track(&self, id: TrackId<'_>) -> Result<FullTrack>;
+    async fn album(&self, id: AlbumId<'_>) -> Result<FullAlbum>;
+    async fn playlist(&self, id: PlaylistId<'_>) -> Result<FullPlaylist>;
+    async fn artist(&self, id: ArtistId<'_>) -> Result<FullArtist>;
+    async fn show(&self, id: ShowId<'_>) -> Result<FullShow>;
+    async fn episode(&self, id: EpisodeId<'_>) -> Result<FullEpisode>;
+    async fn album_tracks(&self, id: AlbumId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedTrack>>;
+    async fn show_episodes(&self, id: ShowId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedEpisode>>;
+
+    async fn save_tracks(&self, ids: Vec<TrackId<'_>>) -> Result<()>;
+    async fn unsave_tracks(&self, ids: Vec<TrackId<'_>>) -> Result<()>;
+    async fn saved_tracks(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SavedTrack>>;
+    async fn saved_albums(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SavedAlbum>>;
+    async fn saved_shows(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<Show>>;
+
+    async fn my_playlists(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedPlaylist>>;
+    async fn playlist_items(&self, id: PlaylistId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<PlaylistItem>>;
+    async fn playlist_create(&self, name: &str, public: bool) -> Result<FullPlaylist>;
+    async fn playlist_add(&self, id: PlaylistId<'_>, items: Vec<PlayableId<'_>>) -> Result<()>;
+    // removes the item at a zero-based position, as of the playlist's snapshot_id
+    async fn playlist_remove(&self, id: PlaylistId<'_>, item: PlayableId<'_>, position: u32, snapshot_id: &str) -> Result<()>;
+    // positions are zero-based, insert_before is a position from before the move
+    async fn playlist_reorder(&self, id: PlaylistId<'_>, range_start: u32, insert_before: u32) -> Result<()>;
+    async fn playlist_rename(&self, id: PlaylistId<'_>, name: &str) -> Result<()>;
+
+    // get a new access token, for when spotify rejects the current one
+    async fn refresh_token(&self) -> Result<()>;
+}
+
+// rspotify's client traits are the same for every client type, so the impl is shared
+macro_rules! impl_backend {
+    ($client:ty) => {
+        #[async_trait]
+        impl SpotifyBackend for $client {
+            async fn search(&self, query: &str, _type: SearchType, limit: Option<u32>, offset: Option<u32>) -> Result<SearchResult> {
+                FishifyError::api(BaseClient::search(self, query, _type, None, None, limit, offset).await).await
+            }
+
+            async fn start_uris(&self, ids: Vec<PlayableId<'_>>, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(self.start_uris_playback(ids, device_id, None, None).await).await
+            }
+
+            async fn start_context(&self, id: PlayContextId<'_>, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(self.start_context_playback(id, device_id, None, None).await).await
+            }
+
+            async fn resume(&self, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(self.resume_playback(device_id, None).await).await
+            }
+
+            async fn pause(&self, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(self.pause_playback(device_id).await).await
+            }
+
+            async fn next(&self, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(self.next_track(device_id).await).await
+            }
+
+            async fn previous(&self, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(self.previous_track(device_id).await).await
+            }
+
+            async fn seek(&self, position: Duration, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(self.seek_track(position, device_id).await).await
+            }
+
+            async fn add_to_queue(&self, id: PlayableId<'_>, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(self.add_item_to_queue(id, device_id).await).await
+            }
+
+            async fn volume(&self, level: u8, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(OAuthClient::volume(self, level, device_id).await).await
+            }
+
+            async fn shuffle(&self, state: bool, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(OAuthClient::shuffle(self, state, device_id).await).await
+            }
+
+            async fn repeat(&self, state: RepeatState, device_id: Option<&str>) -> Result<()> {
+                FishifyError::player(OAuthClient::repeat(self, state, device_id).await).await
+            }
+
+            async fn devices(&self) -> Result<Vec<Device>> {
+                FishifyError::api(self.device().await).await
+            }
+
+            async fn transfer(&self, device_id: &str) -> Result<()> {
+                FishifyError::api(self.transfer_playback(device_id, None).await).await
+            }
+
+            async fn current_playback(&self) -> Result<Option<CurrentPlaybackContext>> {
+                FishifyError::api(OAuthClient::current_playback(self, None, None::<Vec<&AdditionalType>>).await).await
+            }
+
+            async fn current_queue(&self) -> Result<CurrentUserQueue> {
+                FishifyError::api(self.current_user_queue().await).await
+            }
+
+            async fn track(&self, id: TrackId<'_>) -> Result<FullTrack> {
+                FishifyError::api(BaseClient::track(self, id).await).await
+            }
+
+            async fn album(&self, id: AlbumId<'_>) -> Result<FullAlbum> {
+                FishifyError::api(BaseClient::album(self, id).await).await
+            }
+
+            async fn playlist(&self, id: PlaylistId<'_>) -> Result<FullPlaylist> {
+                FishifyError::api(BaseClient::playlist(self, id, None, None).await).await
+            }
+
+            async fn artist(&self, id: ArtistId<'_>) -> Result<FullArtist> {
+                FishifyError::api(BaseClient::artist(self, id).await).await
+            }
+
+            async fn show(&self, id: ShowId<'_>) -> Result<FullShow> {
+                FishifyError::api(self.get_a_show(id, None).await).await
+            }
+
+            async fn episode(&self, id: EpisodeId<'_>) -> Result<FullEpisode> {
+                FishifyError::api(self.get_an_episode(id, None).await).await
+            }
+
+            async fn album_tracks(&self, id: AlbumId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedTrack>> {
+                FishifyError::api(self.album_track_manual(id, None, limit, offset).await).await
+            }
+
+            async fn show_episodes(&self, id: ShowId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedEpisode>> {
+                FishifyError::api(self.get_shows_episodes_manual(id, None, limit, offset).await).await
+            }
+
+            async fn save_tracks(&self, ids: Vec<TrackId<'_>>) -> Result<()> {
+                FishifyError::api(self.current_user_saved_tracks_add(ids).await).await
+            }
+
+            async fn unsave_tracks(&self, ids: Vec<TrackId<'_>>) -> Result<()> {
+                FishifyError::api(self.current_user_saved_tracks_delete(ids).await).await
+            }
+
+            async fn saved_tracks(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SavedTrack>> {
+                FishifyError::api(self.current_user_saved_tracks_manual(None, limit, offset).await).await
+            }
+
+            async fn saved_albums(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SavedAlbum>> {
+                FishifyError::api(self.current_user_saved_albums_manual(None, limit, offset).await).await
+            }
+
+            async fn saved_shows(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<Show>> {
+                FishifyError::api(self.get_saved_show_manual(limit, offset).await).await
+            }
+
+            async fn my_playlists(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedPlaylist>> {
+                FishifyError::api(self.current_user_playlists_manual(limit, offset).await).await
+            }
+
+            async fn playlist_items(&self, id: PlaylistId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<PlaylistItem>> {
+                FishifyError::api(self.playlist_items_manual(id, None, None, limit, offset).await).await
+            }
+
+            async fn playlist_create(&self, name: &str, public: bool) -> Result<FullPlaylist> {
+                let user = FishifyError::api(self.me().await).await?;
+                FishifyError::api(self.user_playlist_create(user.id, name, Some(public), None, None).await).await
+            }
+
+            async fn playlist_add(&self, id: PlaylistId<'_>, items: Vec<PlayableId<'_>>) -> Result<()> {
+                FishifyError::api(self.playlist_add_items(id, items, None).await).await?;
+                Ok(())
+            }
+
+            async fn playlist_remove(&self, id: PlaylistId<'_>, item: PlayableId<'_>, position: u32, snapshot_id: &str) -> Result<()> {
+                let positions = [position];
+                let item = ItemPositions { id: item, positions: &positions };
+                FishifyError::api(self.playlist_remove_specific_occurrences_of_items(id, [item], Some(snapshot_id)).await).await?;
+                Ok(())
+            }
+
+            async fn playlist_reorder(&self, id: PlaylistId<'_>, range_start: u32, insert_before: u32) -> Result<()> {
+                FishifyError::api(self.playlist_reorder_items(id, Some(range_start as i32), Some(insert_before as i32), None, None).await).await?;
+                Ok(())
+            }
+
+            async fn playlist_rename(&self, id: PlaylistId<'_>, name: &str) -> Result<()> {
+                FishifyError::api(self.playlist_change_detail(id, Some(name), None, None, None).await).await?;
+                Ok(())
+            }
+
+            async fn refresh_token(&self) -> Result<()> {
+                FishifyError::api(BaseClient::refresh_token(self).await).await
+            }
+        }
+    };
+}
+
+impl_backend!(AuthCodeSpotify);
+impl_backend!(AuthCodePkceSpotify);
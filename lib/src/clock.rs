@@ -0,0 +1,186 @@
+use crate::error::FishifyError;
+
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+use time::Duration;
+
+// formats as h:mm:ss, or m:ss under an hour
+pub fn duration_clock_format(duration: Duration) -> String {
+    let total_sec = duration.num_seconds();
+    let h = total_sec / 60 / 60;
+    let m = total_sec / 60 % 60;
+    let s = total_sec % 60;
+
+    if h > 0 {
+        format!("{h}:{m:0>2}:{s:0>2}")
+    } else {
+        format!("{m}:{s:0>2}")
+    }
+}
+
+// the inverse of duration_clock_format, also accepts plain seconds with an optional 's' ("90", "90s")
+pub fn parse_clock(s: &str) -> Result<Duration, FishifyError> {
+    let invalid = || FishifyError::InvalidTime(s.to_string());
+
+    if let Some(secs) = s.strip_suffix('s') {
+        let secs: i64 = secs.parse().map_err(|_| invalid())?;
+        if secs < 0 {
+            return Err(invalid());
+        }
+        return Ok(Duration::seconds(secs));
+    }
+
+    let parts = s.split(':')
+        .map(|x| x.parse::<i64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<i64>, FishifyError>>()?;
+    if parts.len() > 3 || parts.iter().any(|x| *x < 0) {
+        return Err(invalid());
+    }
+    // a part after the first can't be 60 or more, "1:75" is probably a typo
+    if parts.iter().skip(1).any(|x| *x >= 60) {
+        return Err(invalid());
+    }
+
+    let total_sec = parts.iter().fold(0, |total, x| total * 60 + x);
+    Ok(Duration::seconds(total_sec))
+}
+
+// a position to seek to within a track
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    // from the start of the track, "1:30"
+    Absolute(Duration),
+    // from the current position, "+15s", "-30s"
+    Relative(Duration),
+    // percentage of the track's duration, "50%"
+    Percent(f64),
+}
+
+impl Position {
+    // the position from the start of the track, clamped to the track
+    pub fn resolve(&self, progress: Duration, duration: Duration) -> Duration {
+        let position = match self {
+            Self::Absolute(position) => *position,
+            Self::Relative(offset) => progress + *offset,
+            Self::Percent(percent) => Duration::milliseconds((duration.num_milliseconds() as f64 * percent / 100.0) as i64),
+        };
+        position.max(Duration::zero()).min(duration)
+    }
+}
+
+impl FromStr for Position {
+    type Err = FishifyError;
+
+    fn from_str(s: &str) -> Result<Self, FishifyError> {
+        let s = s.trim();
+
+        if let Some(percent) = s.strip_suffix('%') {
+            let invalid = || FishifyError::InvalidPercentage(s.to_string());
+            let percent: f64 = percent.parse().map_err(|_| invalid())?;
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(invalid());
+            }
+            Ok(Self::Percent(percent))
+        } else if let Some(offset) = s.strip_prefix('+') {
+            Ok(Self::Relative(parse_clock(offset)?))
+        } else if let Some(offset) = s.strip_prefix('-') {
+            Ok(Self::Relative(-parse_clock(offset)?))
+        } else {
+            Ok(Self::Absolute(parse_clock(s)?))
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Absolute(position) => write!(f, "{}", duration_clock_format(*position)),
+            Self::Relative(offset) if *offset < Duration::zero() => write!(f, "-{}", duration_clock_format(-*offset)),
+            Self::Relative(offset) => write!(f, "+{}", duration_clock_format(*offset)),
+            Self::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_round_trip() {
+        for secs in [0, 5, 59, 60, 90, 599, 3599, 3600, 3661, 36000] {
+            let duration = Duration::seconds(secs);
+            assert_eq!(parse_clock(&duration_clock_format(duration)).unwrap(), duration);
+        }
+        assert_eq!(duration_clock_format(Duration::seconds(90)), "1:30");
+        assert_eq!(duration_clock_format(Duration::seconds(3723)), "1:02:03");
+    }
+
+    #[test]
+    fn clock_seconds() {
+        assert_eq!(parse_clock("90").unwrap(), Duration::seconds(90));
+        assert_eq!(parse_clock("90s").unwrap(), Duration::seconds(90));
+        assert_eq!(parse_clock("0:05").unwrap(), Duration::seconds(5));
+    }
+
+    #[test]
+    fn clock_invalid() {
+        for s in ["", "s", "abc", "1:75", "1:02:75", "1:2:3:4", "-5", "-5s", "1:-5", "1.5"] {
+            assert_eq!(parse_clock(s), Err(FishifyError::InvalidTime(s.to_string())));
+        }
+    }
+
+    #[test]
+    fn position_parse() {
+        assert_eq!("1:30".parse::<Position>().unwrap(), Position::Absolute(Duration::seconds(90)));
+        assert_eq!("+15s".parse::<Position>().unwrap(), Position::Relative(Duration::seconds(15)));
+        assert_eq!("-0:30".parse::<Position>().unwrap(), Position::Relative(Duration::seconds(-30)));
+        assert_eq!(" 50% ".parse::<Position>().unwrap(), Position::Percent(50.0));
+        assert_eq!("0%".parse::<Position>().unwrap(), Position::Percent(0.0));
+        assert_eq!("100%".parse::<Position>().unwrap(), Position::Percent(100.0));
+    }
+
+    #[test]
+    fn position_single_sign() {
+        assert_eq!("--5s".parse::<Position>(), Err(FishifyError::InvalidTime("-5s".to_string())));
+        assert_eq!("+-5s".parse::<Position>(), Err(FishifyError::InvalidTime("-5s".to_string())));
+    }
+
+    #[test]
+    fn position_percent_bounds() {
+        for s in ["-1%", "100.5%", "150%", "%", "half%"] {
+            assert_eq!(s.parse::<Position>(), Err(FishifyError::InvalidPercentage(s.to_string())));
+        }
+    }
+
+    #[test]
+    fn position_display_round_trip() {
+        for position in [Position::Absolute(Duration::seconds(90)), Position::Relative(Duration::seconds(15)), Position::Relative(Duration::seconds(-30)), Position::Percent(25.0)] {
+            assert_eq!(position.to_string().parse::<Position>().unwrap(), position);
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let progress = Duration::seconds(60);
+        let duration = Duration::seconds(200);
+        assert_eq!(Position::Absolute(Duration::seconds(90)).resolve(progress, duration), Duration::seconds(90));
+        assert_eq!(Position::Relative(Duration::seconds(15)).resolve(progress, duration), Duration::seconds(75));
+        assert_eq!(Position::Relative(Duration::seconds(-30)).resolve(progress, duration), Duration::seconds(30));
+        assert_eq!(Position::Percent(50.0).resolve(progress, duration), Duration::seconds(100));
+    }
+
+    #[test]
+    fn resolve_clamps() {
+        let progress = Duration::seconds(60);
+        let duration = Duration::seconds(200);
+        assert_eq!(Position::Absolute(Duration::seconds(500)).resolve(progress, duration), duration);
+        assert_eq!(Position::Relative(Duration::seconds(300)).resolve(progress, duration), duration);
+        assert_eq!(Position::Relative(Duration::seconds(-90)).resolve(progress, duration), Duration::zero());
+        assert_eq!(Position::Percent(100.0).resolve(progress, duration), duration);
+        assert_eq!(Position::Percent(0.0).resolve(progress, duration), Duration::zero());
+    }
+}
@@ -1,27 +1,54 @@
+use crate::error::FishifyError;
+
 use serde::{Deserialize, Serialize};
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use std::{
     fs,
     path::{Path, PathBuf},
 };
 
 const DEFAULT_PORT: u16 = 8888;
+const DEFAULT_API_URL: &str = "https://api.spotify.com/v1/";
 const CONFIG_FILE: &str = "client.yml";
 const CONFIG_DIR: &str = ".config";
 const APP_CONFIG_DIR: &str = "fishify";
 const TOKEN_CACHE_FILE: &str = ".spotify_token_cache.json";
+const PENDING_LOGIN_FILE: &str = ".spotify_pending_login.json";
+
+// How fishify logs in to spotify
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    // the authorization code flow, which needs the app's client secret
+    #[default]
+    Code,
+    // the authorization code flow with pkce, which only needs the client id, so it can be shared
+    Pkce,
+}
 
 #[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ClientConfig {
     pub client_id: String,
+    // not needed with `auth: pkce`
+    #[serde(default)]
     pub client_secret: String,
+    #[serde(default)]
+    pub auth: AuthMode,
     pub device_id: Option<String>,
+    // used when device_id isn't set, since ids can change when a device is reinstalled
+    pub device_name: Option<String>,
     pub port: Option<u16>,
+    // for pointing fishify at something other than spotify, like fishify-fake-api
+    pub api_url: Option<String>,
+    // the most items to queue from an album, playlist or show, all of them if unset
+    pub max_context_items: Option<u32>,
 }
 
 pub struct ConfigPaths {
     pub config_file_path: PathBuf,
     pub token_cache_path: PathBuf,
+    // a login waiting for spotify's redirect, see auth::Pending
+    pub pending_login_path: PathBuf,
 }
 
 impl ClientConfig {
@@ -29,8 +56,12 @@ impl ClientConfig {
         ClientConfig {
             client_id: "".to_string(),
             client_secret: "".to_string(),
+            auth: AuthMode::Code,
             device_id: None,
+            device_name: None,
             port: None,
+            api_url: None,
+            max_context_items: None,
         }
     }
 
@@ -42,6 +73,15 @@ impl ClientConfig {
         self.port.unwrap_or(DEFAULT_PORT)
     }
 
+    // the preferred device, by id or by name
+    pub fn get_device(&self) -> Option<String> {
+        self.device_id.clone().or(self.device_name.clone())
+    }
+
+    pub fn get_api_url(&self) -> String {
+        self.api_url.clone().unwrap_or(DEFAULT_API_URL.to_string())
+    }
+
     pub fn get_or_build_paths(&self) -> Result<ConfigPaths> {
         match dirs::home_dir() {
             Some(home) => {
@@ -59,15 +99,17 @@ impl ClientConfig {
 
                 let config_file_path = &app_config_dir.join(CONFIG_FILE);
                 let token_cache_path = &app_config_dir.join(TOKEN_CACHE_FILE);
+                let pending_login_path = &app_config_dir.join(PENDING_LOGIN_FILE);
 
                 let paths = ConfigPaths {
                     config_file_path: config_file_path.to_path_buf(),
                     token_cache_path: token_cache_path.to_path_buf(),
+                    pending_login_path: pending_login_path.to_path_buf(),
                 };
 
                 Ok(paths)
             } 
-            None => Err(anyhow!("No $HOME directory found for client config")),
+            None => Err(FishifyError::Config("No $HOME directory found for client config".to_string()).into()),
         }
     }
 
@@ -79,11 +121,20 @@ impl ClientConfig {
 
             self.client_id = config.client_id;
             self.client_secret = config.client_secret;
+            self.auth = config.auth;
             self.device_id = config.device_id;
+            self.device_name = config.device_name;
+            self.port = config.port;
+            self.api_url = config.api_url;
+            self.max_context_items = config.max_context_items;
+
+            if self.auth == AuthMode::Code && self.client_secret.is_empty() {
+                return Err(FishifyError::Config(format!("Configure client_secret in {}, or set `auth: pkce`", paths.config_file_path.display())).into());
+            }
 
             Ok(())
         } else {
-            Err(anyhow!("Configure client_id and client_secret in {}", paths.config_file_path.display()))
+            Err(FishifyError::Config(format!("Configure client_id and client_secret in {}", paths.config_file_path.display())).into())
         }
     }
 }
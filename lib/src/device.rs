@@ -0,0 +1,186 @@
+use crate::error::FishifyError;
+
+use anyhow::Result;
+use rspotify::model::device::Device;
+
+// How well a device matches what someone typed, lower is better. The first field is how the
+// device matched, the second orders fuzzy matches by how spread out the typed letters are.
+type Score = (u8, usize);
+
+const EXACT: u8 = 0;
+const NAME: u8 = 1;
+const TYPE: u8 = 2;
+const ID_PREFIX: u8 = 3;
+const FUZZY: u8 = 4;
+
+// Finds the device someone meant by `target`, trying the most specific match first: the exact
+// id or name, part of the name, the device type like "computer" or "smartphone", the start of
+// the id, then the letters of the name in order. Several devices matching equally well is an
+// error listing them, instead of picking one at random.
+pub fn find_device(devices: Vec<Device>, target: &str) -> Result<Device> {
+    let mut scored: Vec<(Score, Device)> = devices.into_iter()
+        .filter_map(|device| score(&device, target).map(|score| (score, device)))
+        .collect();
+    scored.sort_by_key(|(score, _)| *score);
+
+    let best = match scored.first() {
+        Some((score, _)) => *score,
+        None => return Err(FishifyError::DeviceNotFound(target.to_string()).into()),
+    };
+    let mut matches: Vec<Device> = scored.into_iter()
+        .take_while(|(score, _)| *score == best)
+        .map(|(_, device)| device)
+        .collect();
+
+    if matches.len() > 1 {
+        let names = matches.into_iter().map(|device| device.name).collect();
+        return Err(FishifyError::AmbiguousDevice(names).into());
+    }
+    Ok(matches.remove(0))
+}
+
+// Devices matching `target`, best first, for suggesting devices while someone types.
+// Everything matches an empty target.
+pub fn candidates<'a>(devices: &'a [Device], target: &str) -> Vec<&'a Device> {
+    if target.is_empty() {
+        return devices.iter().collect();
+    }
+    let mut scored: Vec<(Score, &Device)> = devices.iter()
+        .filter_map(|device| score(device, target).map(|score| (score, device)))
+        .collect();
+    scored.sort_by_key(|(score, _)| *score);
+    scored.into_iter().map(|(_, device)| device).collect()
+}
+
+fn score(device: &Device, target: &str) -> Option<Score> {
+    let target = target.to_lowercase();
+    let name = device.name.to_lowercase();
+    let id = device.id.as_deref().unwrap_or_default().to_lowercase();
+    let _type = format!("{:?}", device._type).to_lowercase();
+
+    if name == target || id == target {
+        Some((EXACT, 0))
+    } else if name.contains(&target) {
+        Some((NAME, 0))
+    } else if _type == target {
+        Some((TYPE, 0))
+    } else if !id.is_empty() && id.starts_with(&target) {
+        Some((ID_PREFIX, 0))
+    } else {
+        fuzzy(&name, &target).map(|gaps| (FUZZY, gaps))
+    }
+}
+
+// The number of skipped characters if every character of target appears in name in order,
+// so "kspk" matches "kitchen speaker" but "spkk" doesn't
+fn fuzzy(name: &str, target: &str) -> Option<usize> {
+    let mut chars = name.chars();
+    let mut gaps = 0;
+    for wanted in target.chars().filter(|x| !x.is_whitespace()) {
+        loop {
+            match chars.next() {
+                Some(x) if x == wanted => break,
+                Some(_) => gaps += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(gaps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn device(name: &str, id: &str, _type: &str) -> Device {
+        serde_json::from_value(json!({
+            "id": id,
+            "is_active": false,
+            "is_private_session": false,
+            "is_restricted": false,
+            "name": name,
+            "type": _type,
+            "volume_percent": 50,
+        })).unwrap()
+    }
+
+    fn devices() -> Vec<Device> {
+        vec![
+            device("Kitchen Speaker", "a1b2c3", "Speaker"),
+            device("Bedroom Speaker", "d4e5f6", "Speaker"),
+            device("Work Laptop", "0f9e8d", "Computer"),
+            device("Pixel", "7c6b5a", "Smartphone"),
+        ]
+    }
+
+    fn found(target: &str) -> String {
+        find_device(devices(), target).unwrap().name
+    }
+
+    #[test]
+    fn name() {
+        assert_eq!(found("kitchen"), "Kitchen Speaker");
+        assert_eq!(found("Kitchen Speaker"), "Kitchen Speaker");
+        assert_eq!(found("LAPTOP"), "Work Laptop");
+    }
+
+    #[test]
+    fn type_and_id() {
+        assert_eq!(found("smartphone"), "Pixel");
+        assert_eq!(found("computer"), "Work Laptop");
+        assert_eq!(found("d4e5f6"), "Bedroom Speaker");
+        assert_eq!(found("0f9"), "Work Laptop");
+    }
+
+    #[test]
+    fn fuzzy_match() {
+        assert_eq!(found("kspk"), "Kitchen Speaker");
+        assert_eq!(found("wlptp"), "Work Laptop");
+        assert_eq!(fuzzy("kitchen speaker", "kspk"), Some(9));
+        assert_eq!(fuzzy("kitchen speaker", "spkk"), None);
+        // whitespace in what was typed is ignored
+        assert_eq!(fuzzy("work laptop", "w l"), Some(4));
+    }
+
+    #[test]
+    fn ambiguous() {
+        let err = find_device(devices(), "speaker").unwrap_err();
+        let names = vec!["Kitchen Speaker".to_string(), "Bedroom Speaker".to_string()];
+        assert_eq!(err.downcast_ref::<FishifyError>(), Some(&FishifyError::AmbiguousDevice(names)));
+    }
+
+    #[test]
+    fn exact_beats_partial() {
+        let mut devices = devices();
+        devices.push(device("Speaker", "9a8b7c", "Speaker"));
+        assert_eq!(find_device(devices, "speaker").unwrap().name, "Speaker");
+    }
+
+    #[test]
+    fn not_found() {
+        let err = find_device(devices(), "garage").unwrap_err();
+        assert_eq!(err.downcast_ref::<FishifyError>(), Some(&FishifyError::DeviceNotFound("garage".to_string())));
+    }
+
+    #[test]
+    fn ranking() {
+        let devices = vec![
+            device("Desk", "5d5d5d", "Computer"),
+            device("Kitchen Display", "e1e1e1", "Speaker"),
+            device("Old Speaker", "abcdef", "Speaker"),
+            device("Speaker", "123456", "Smartphone"),
+        ];
+        let names: Vec<&str> = candidates(&devices, "speaker").into_iter().map(|x| x.name.as_str()).collect();
+        // the exact name, then part of the name, then the type
+        assert_eq!(names, ["Speaker", "Old Speaker", "Kitchen Display"]);
+
+        let names: Vec<&str> = candidates(&devices, "").into_iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names.len(), 4);
+
+        // fuzzy matches with fewer skipped letters first
+        let names: Vec<&str> = candidates(&devices, "dk").into_iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["Desk", "Old Speaker"]);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::model::SkipReason;
+
+use std::fmt;
+
+use rspotify::{
+    ClientError,
+    http::HttpError,
+};
+
+// Errors fishify knows how to explain. Fishify's methods return these, so front-ends can match
+// on them; anything else, like a network error, is Other with the original error's message.
+// The backends still return anyhow::Result, with these inside where there's a matching variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FishifyError {
+    // nothing is playing, and no device is active to play on
+    NoActiveDevice,
+    // the name that was looked for
+    DeviceNotFound(String),
+    // the names of the devices that matched equally well
+    AmbiguousDevice(Vec<String>),
+    NoDevices,
+    NoSearchResults,
+    InvalidUrl(String),
+    InvalidUri(String),
+    // a device is active, but it isn't playing a track or episode
+    NothingPlaying,
+    PlaylistNotFound(String),
+    // a one-based position that's out of range
+    InvalidPosition(u32),
+    NoLikedSongs,
+    Unplayable(SkipReason),
+    // the token is missing, expired, or was revoked
+    Unauthorized,
+    // seconds to wait, if spotify said
+    RateLimited { retry_after: Option<u64> },
+    // spotify only lets premium users control playback
+    PremiumRequired,
+    // any other status code from the web api
+    Api(u16),
+    Config(String),
+    // a redirected url was given, but no login was started or it was for another one
+    NoPendingLogin,
+    // the device's name, spotify gives no id for devices that can't be controlled through the api
+    RestrictedDevice(String),
+    // only albums, playlists and shows have items to page through
+    NotAContext,
+    // what was given, for a seek position
+    InvalidTime(String),
+    InvalidPercentage(String),
+    Other(String),
+}
+
+impl FishifyError {
+    // Converts an error from rspotify for the player endpoints, where a 404 with the
+    // NO_ACTIVE_DEVICE reason means there's no device to play on. Other errors are passed
+    // through unchanged.
+    pub async fn from_player(err: ClientError) -> anyhow::Error {
+        match Self::from_status(err).await {
+            Ok((Self::Api(404), Some(reason))) if reason == "NO_ACTIVE_DEVICE" => Self::NoActiveDevice.into(),
+            Ok((fishify_err, _)) => fishify_err.into(),
+            Err(err) => err.into(),
+        }
+    }
+
+    // Converts an error from rspotify, for endpoints that aren't about playback.
+    pub async fn from_api(err: ClientError) -> anyhow::Error {
+        match Self::from_status(err).await {
+            Ok((fishify_err, _)) => fishify_err.into(),
+            Err(err) => err.into(),
+        }
+    }
+
+    // from_player and from_api for a whole result, so they fit in a single expression
+    pub async fn player<T>(result: Result<T, ClientError>) -> anyhow::Result<T> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(err) => Err(Self::from_player(err).await),
+        }
+    }
+
+    pub async fn api<T>(result: Result<T, ClientError>) -> anyhow::Result<T> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(err) => Err(Self::from_api(err).await),
+        }
+    }
+
+    // The error for a status code, and the reason spotify gave in the body, like
+    // {"error": {"status": 403, "reason": "PREMIUM_REQUIRED"}}. Statuses are shared by
+    // different errors, so only the reason tells them apart. Errors without a status are
+    // given back.
+    async fn from_status(err: ClientError) -> Result<(Self, Option<String>), ClientError> {
+        let response = match err {
+            ClientError::InvalidToken => return Ok((Self::Unauthorized, None)),
+            ClientError::Http(http) => match *http {
+                HttpError::StatusCode(response) => response,
+                http => return Err(ClientError::Http(Box::new(http))),
+            },
+            err => return Err(err),
+        };
+
+        let status = response.status().as_u16();
+        let retry_after = response.headers().get("retry-after")
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.parse().ok());
+        let reason = response.text().await.ok()
+            .and_then(|body| serde_json::from_str::<serde_json::Value>(&body).ok())
+            .and_then(|body| body["error"]["reason"].as_str().map(|x| x.to_string()));
+
+        let fishify_err = match status {
+            401 => Self::Unauthorized,
+            403 if reason.as_deref() == Some("PREMIUM_REQUIRED") => Self::PremiumRequired,
+            429 => Self::RateLimited { retry_after },
+            status => Self::Api(status),
+        };
+        Ok((fishify_err, reason))
+    }
+}
+
+impl fmt::Display for FishifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoActiveDevice => write!(f, "No active device, connect to one first"),
+            Self::DeviceNotFound(name) => write!(f, "Device not found: {name}"),
+            Self::AmbiguousDevice(names) => write!(f, "Several devices match, pick one of: {}", names.join(", ")),
+            Self::NoDevices => write!(f, "No devices found"),
+            Self::NoSearchResults => write!(f, "No search result"),
+            Self::InvalidUrl(url) => write!(f, "Invalid url: {url}"),
+            Self::InvalidUri(uri) => write!(f, "Invalid uri: {uri}"),
+            Self::NothingPlaying => write!(f, "Not playing a track or episode"),
+            Self::PlaylistNotFound(name) => write!(f, "Playlist not found: {name}"),
+            Self::InvalidPosition(position) => write!(f, "No item at position {position}, positions start at 1"),
+            Self::NoLikedSongs => write!(f, "No liked songs"),
+            Self::Unplayable(reason) => write!(f, "{reason}"),
+            Self::Unauthorized => write!(f, "Not authorized by spotify, try logging in again"),
+            Self::RateLimited { retry_after: Some(secs) } => write!(f, "Rate limited by spotify, try again in {secs} seconds"),
+            Self::RateLimited { retry_after: None } => write!(f, "Rate limited by spotify, try again later"),
+            Self::PremiumRequired => write!(f, "Spotify premium is required to control playback"),
+            Self::Api(status) => write!(f, "Spotify api error, status {status}"),
+            Self::Config(message) => write!(f, "{message}"),
+            Self::NoPendingLogin => write!(f, "That url isn't from the latest login, log in again for a new one"),
+            Self::RestrictedDevice(name) => write!(f, "{name} can't be controlled through spotify's api"),
+            Self::NotAContext => write!(f, "Only albums, playlists and shows can be paged through"),
+            Self::InvalidTime(time) => write!(f, "Invalid time '{time}', must be like '1:30', '1:02:03', or '90s'"),
+            Self::InvalidPercentage(percent) => write!(f, "Invalid percentage '{percent}', must be between 0 and 100"),
+            Self::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for FishifyError {}
+
+impl From<SkipReason> for FishifyError {
+    fn from(reason: SkipReason) -> Self {
+        return Self::Unplayable(reason);
+    }
+}
+
+// the fishify error inside a backend's error, or Other with its message
+impl From<anyhow::Error> for FishifyError {
+    fn from(err: anyhow::Error) -> Self {
+        if let Some(reason) = err.downcast_ref::<SkipReason>() {
+            return Self::Unplayable(*reason);
+        }
+        match err.downcast::<FishifyError>() {
+            Ok(fishify_err) => fishify_err,
+            Err(err) => Self::Other(format!("{err:#}")),
+        }
+    }
+}
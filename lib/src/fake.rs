@@ -0,0 +1,811 @@
+use crate::{
+    backend::SpotifyBackend,
+    error::FishifyError,
+};
+
+use std::{
+    collections::{ HashMap, VecDeque, },
+    sync::{ Mutex, MutexGuard, },
+};
+
+use anyhow::{ anyhow, Result, };
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use time::Duration;
+use serde_json::{ json, Value, };
+use rspotify::{
+    model::{
+        track::{ FullTrack, SavedTrack, SimplifiedTrack, },
+        album::{ FullAlbum, SavedAlbum, },
+        playlist::{ FullPlaylist, SimplifiedPlaylist, PlaylistItem, },
+        artist::FullArtist,
+        show::{ FullShow, FullEpisode, SimplifiedEpisode, Show, },
+        page::Page,
+        idtypes::{ TrackId, AlbumId, PlaylistId, ArtistId, ShowId, EpisodeId, },
+        context::{ CurrentPlaybackContext, CurrentUserQueue, },
+        device::Device,
+        enums::{
+            types::{ SearchType, DeviceType, },
+            misc::{ RepeatState, },
+        },
+        search::SearchResult,
+    },
+    prelude::{ Id, PlayContextId, PlayableId, },
+};
+
+// An in-memory spotify player. Content is added with the `add_*` methods, and playback
+// commands move through it the way the real player would, so Fishify can run without a network.
+// The json builders mirror the web api's responses, and the rspotify models are parsed from them.
+#[derive(Debug)]
+pub struct FakePlayer {
+    state: Mutex<FakeState>,
+}
+
+#[derive(Debug)]
+pub struct FakeState {
+    next_id: u32,
+    // content by uri, stored as the json the web api would return
+    pub content: HashMap<String, Value>,
+    pub devices: Vec<Device>,
+    pub active_device: Option<String>,
+    pub context: Option<String>,
+    pub current: Option<String>,
+    // the rest of the playing context
+    pub upcoming: VecDeque<String>,
+    // items added with add_to_queue, played before the rest of the context
+    pub queue: VecDeque<String>,
+    // played items, most recent last
+    pub history: Vec<String>,
+    // the user's library, as uris, most recently saved first
+    pub saved: Vec<String>,
+    pub is_playing: bool,
+    pub progress_ms: i64,
+    pub volume: u32,
+    pub shuffle: bool,
+    pub repeat: RepeatState,
+    // every call to the backend, and failures to return from the next ones instead, oldest first
+    pub calls: u32,
+    pub failures: VecDeque<FishifyError>,
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T> {
+    Ok(serde_json::from_value(value)?)
+}
+
+fn page(items: Vec<Value>) -> Value {
+    json!({
+        "href": "",
+        "limit": items.len(),
+        "next": null,
+        "offset": 0,
+        "previous": null,
+        "total": items.len(),
+        "items": items,
+    })
+}
+
+// one page out of a list of items, like the web api's paging endpoints
+fn offset_page(items: Vec<Value>, limit: Option<u32>, offset: Option<u32>, default_limit: u32) -> Value {
+    let total = items.len();
+    let mut page = page(items.into_iter()
+        .skip(offset.unwrap_or(0) as usize)
+        .take(limit.unwrap_or(default_limit) as usize)
+        .collect());
+    page["offset"] = json!(offset.unwrap_or(0));
+    page["total"] = json!(total);
+    page
+}
+
+fn playlist_item(track: Value) -> Value {
+    json!({
+        "added_at": null,
+        "added_by": null,
+        "is_local": false,
+        "track": track,
+    })
+}
+
+fn simplified_artist(name: &str, id: &str) -> Value {
+    json!({
+        "external_urls": {},
+        "href": null,
+        "id": id,
+        "name": name,
+    })
+}
+
+impl FakeState {
+    fn new_id(&mut self) -> String {
+        self.next_id += 1;
+        // spotify ids are alphanumeric, rspotify checks that
+        format!("fake{:018}", self.next_id)
+    }
+
+    fn get(&self, uri: &str) -> Result<Value> {
+        self.content.get(uri).cloned().ok_or(anyhow!("Not found: {uri}"))
+    }
+
+    // the device a command targets, the active one if none is given
+    fn device(&self, device_id: Option<&str>) -> Result<String> {
+        match device_id {
+            Some(id) => {
+                self.devices.iter().find(|x| x.id.as_deref() == Some(id)).ok_or(FishifyError::DeviceNotFound(id.to_string()))?;
+                Ok(id.to_string())
+            },
+            None => self.active_device.clone().ok_or(FishifyError::NoActiveDevice.into()),
+        }
+    }
+
+    // every device, with is_active set for the active one
+    fn device_list(&self) -> Vec<Device> {
+        let mut devices = self.devices.clone();
+        for dev in devices.iter_mut() {
+            dev.is_active = dev.id.is_some() && dev.id == self.active_device;
+        }
+        devices
+    }
+
+    // the playable uris of a context, in order
+    fn context_items(&self, uri: &str) -> Result<Vec<String>> {
+        let content = self.get(uri)?;
+        let items = match content["type"].as_str() {
+            Some("album") => content["tracks"]["items"].as_array().cloned().unwrap_or_default(),
+            Some("show") => content["episodes"]["items"].as_array().cloned().unwrap_or_default(),
+            Some("playlist") => content["tracks"]["items"].as_array().cloned().unwrap_or_default()
+                .into_iter().map(|x| x["track"].clone()).collect(),
+            Some("artist") => self.content.values().filter(|x| {
+                x["type"] == "track" && x["artists"].as_array().is_some_and(|a| a.iter().any(|a| a["id"] == content["id"]))
+            }).cloned().collect(),
+            _ => return Err(anyhow!("Not a context: {uri}")),
+        };
+        Ok(items.iter().filter_map(|x| x["uri"].as_str().map(|x| x.to_string())).collect())
+    }
+
+    fn start(&mut self, device_id: Option<&str>, context: Option<String>, mut items: VecDeque<String>) -> Result<()> {
+        self.active_device = Some(self.device(device_id)?);
+        self.context = context;
+        self.current = items.pop_front();
+        self.upcoming = items;
+        self.is_playing = self.current.is_some();
+        self.progress_ms = 0;
+        Ok(())
+    }
+
+    // repeating a track replays it, repeating a context puts the current item back at the end of
+    // it before taking the next one, so a context of one item loops too
+    fn advance(&mut self) {
+        if self.repeat == RepeatState::Track && self.current.is_some() {
+            self.is_playing = true;
+            self.progress_ms = 0;
+            return;
+        }
+        if let Some(current) = self.current.take() {
+            if self.repeat == RepeatState::Context && self.context.is_some() {
+                self.upcoming.push_back(current.clone());
+            }
+            self.history.push(current);
+        }
+        self.current = self.queue.pop_front().or_else(|| self.upcoming.pop_front());
+        self.is_playing = self.current.is_some();
+        self.progress_ms = 0;
+    }
+
+    fn back(&mut self) {
+        if let Some(previous) = self.history.pop() {
+            if let Some(current) = self.current.take() {
+                self.upcoming.push_front(current);
+            }
+            self.current = Some(previous);
+        }
+        self.progress_ms = 0;
+    }
+}
+
+impl Default for FakePlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakePlayer {
+    pub fn new() -> Self {
+        return Self {
+            state: Mutex::new(FakeState {
+                next_id: 0,
+                content: HashMap::new(),
+                devices: vec![],
+                active_device: None,
+                context: None,
+                current: None,
+                upcoming: VecDeque::new(),
+                queue: VecDeque::new(),
+                history: vec![],
+                saved: vec![],
+                is_playing: false,
+                progress_ms: 0,
+                volume: 100,
+                shuffle: false,
+                repeat: RepeatState::Off,
+                calls: 0,
+                failures: VecDeque::new(),
+            }),
+        };
+    }
+
+    // the state is public so tests can inspect it, or set it up directly
+    pub fn state(&self) -> MutexGuard<FakeState> {
+        self.state.lock().unwrap()
+    }
+
+    // makes the next call fail with err, after any earlier injected failures
+    pub fn fail_next(&self, err: FishifyError) {
+        self.state().failures.push_back(err);
+    }
+
+    // counts a backend call, failing it if a failure was injected
+    fn call(&self) -> Result<()> {
+        let mut state = self.state();
+        state.calls += 1;
+        match state.failures.pop_front() {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn insert(&self, value: Value) -> String {
+        let uri = value["uri"].as_str().unwrap().to_string();
+        self.state().content.insert(uri.clone(), value);
+        uri
+    }
+
+    pub fn add_device(&self, name: &str, _type: DeviceType) -> String {
+        let mut state = self.state();
+        let id = state.new_id();
+        let device = json!({
+            "id": id,
+            "is_active": false,
+            "is_private_session": false,
+            "is_restricted": false,
+            "name": name,
+            "type": _type,
+            "volume_percent": 100,
+        });
+        state.devices.push(serde_json::from_value(device).expect("invalid device"));
+        id
+    }
+
+    pub fn add_artist(&self, name: &str) -> String {
+        let id = self.state().new_id();
+        self.insert(json!({
+            "type": "artist",
+            "external_urls": {},
+            "followers": { "href": null, "total": 0 },
+            "genres": [],
+            "href": "",
+            "id": id,
+            "images": [],
+            "name": name,
+            "popularity": 0,
+            "uri": format!("spotify:artist:{id}"),
+        }))
+    }
+
+    // artist is a uri from add_artist
+    pub fn add_track(&self, name: &str, artist: &str, duration_ms: i64) -> String {
+        let artist = self.state().get(artist).expect("unknown artist");
+        let id = self.state().new_id();
+        self.insert(json!({
+            "type": "track",
+            "album": {
+                "album_type": "single",
+                "artists": [simplified_artist(artist["name"].as_str().unwrap(), artist["id"].as_str().unwrap())],
+                "external_urls": {},
+                "href": null,
+                "id": null,
+                "images": [],
+                "name": name,
+            },
+            "artists": [simplified_artist(artist["name"].as_str().unwrap(), artist["id"].as_str().unwrap())],
+            "disc_number": 1,
+            "duration_ms": duration_ms,
+            "explicit": false,
+            "external_ids": {},
+            "external_urls": {},
+            "href": null,
+            "id": id,
+            "is_local": false,
+            "name": name,
+            "popularity": 0,
+            "preview_url": null,
+            "track_number": 1,
+            "uri": format!("spotify:track:{id}"),
+        }))
+    }
+
+    // tracks are uris from add_track
+    pub fn add_album(&self, name: &str, artist: &str, tracks: &[&str]) -> String {
+        let artist = self.state().get(artist).expect("unknown artist");
+        let tracks: Vec<Value> = tracks.iter().map(|x| self.state().get(x).expect("unknown track")).collect();
+        let id = self.state().new_id();
+        self.insert(json!({
+            "type": "album",
+            "album_type": "album",
+            "available_markets": [],
+            "artists": [simplified_artist(artist["name"].as_str().unwrap(), artist["id"].as_str().unwrap())],
+            "copyrights": [],
+            "external_ids": {},
+            "external_urls": {},
+            "genres": [],
+            "href": "",
+            "id": id,
+            "images": [],
+            "name": name,
+            "popularity": 0,
+            "release_date": "2023-01-01",
+            "release_date_precision": "day",
+            "tracks": page(tracks),
+            "uri": format!("spotify:album:{id}"),
+        }))
+    }
+
+    pub fn add_playlist(&self, name: &str, tracks: &[&str]) -> String {
+        let items: Vec<Value> = tracks.iter().map(|x| playlist_item(self.state().get(x).expect("unknown track"))).collect();
+        let id = self.state().new_id();
+        self.insert(json!({
+            "type": "playlist",
+            "collaborative": false,
+            "description": null,
+            "external_urls": {},
+            "followers": { "href": null, "total": 0 },
+            "href": "",
+            "id": id,
+            "images": [],
+            "name": name,
+            "owner": { "display_name": "fake", "external_urls": {}, "href": "", "id": "fake" },
+            "public": true,
+            "snapshot_id": "",
+            "tracks": page(items),
+            "uri": format!("spotify:playlist:{id}"),
+        }))
+    }
+
+    pub fn add_show(&self, name: &str, episodes: &[(&str, i64)]) -> String {
+        let id = self.state().new_id();
+        let show = json!({
+            "available_markets": [],
+            "copyrights": [],
+            "description": "",
+            "explicit": false,
+            "external_urls": {},
+            "href": "",
+            "id": id,
+            "images": [],
+            "is_externally_hosted": null,
+            "languages": [],
+            "media_type": "audio",
+            "name": name,
+            "publisher": "fake",
+        });
+
+        let episodes: Vec<Value> = episodes.iter().map(|(name, duration_ms)| {
+            let id = self.state().new_id();
+            let episode = json!({
+                "type": "episode",
+                "audio_preview_url": null,
+                "description": "",
+                "duration_ms": duration_ms,
+                "explicit": false,
+                "external_urls": {},
+                "href": "",
+                "id": id,
+                "images": [],
+                "is_externally_hosted": false,
+                "is_playable": true,
+                "language": "en",
+                "languages": [],
+                "name": name,
+                "release_date": "2023-01-01",
+                "release_date_precision": "day",
+                "show": show.clone(),
+                "uri": format!("spotify:episode:{id}"),
+            });
+            self.insert(episode.clone());
+            episode
+        }).collect();
+
+        let mut full_show = show;
+        full_show["type"] = json!("show");
+        full_show["episodes"] = page(episodes);
+        full_show["uri"] = json!(format!("spotify:show:{id}"));
+        self.insert(full_show)
+    }
+
+    // add a track, album or show to the library
+    pub fn save(&self, uri: &str) {
+        let mut state = self.state();
+        state.get(uri).expect("unknown content");
+        state.saved.retain(|x| x != uri);
+        state.saved.insert(0, uri.to_string());
+    }
+
+    // a page of saved content of one type, wrapped like the web api does, {"added_at": ..., "track": ...}
+    fn saved_page<T: DeserializeOwned>(&self, _type: &str, limit: Option<u32>, offset: Option<u32>) -> Result<T> {
+        let state = self.state();
+        let items = state.saved.iter()
+            .filter(|x| x.split(':').nth(1) == Some(_type))
+            .skip(offset.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(20) as usize)
+            .map(|uri| Ok(json!({ "added_at": "2023-01-01T00:00:00Z", _type: state.get(uri)? })))
+            .collect::<Result<Vec<Value>>>()?;
+        let total = state.saved.iter().filter(|x| x.split(':').nth(1) == Some(_type)).count();
+
+        let mut page = page(items);
+        page["offset"] = json!(offset.unwrap_or(0));
+        page["total"] = json!(total);
+        parse(page)
+    }
+
+    // edit a playlist's items in place
+    fn edit_playlist<F>(&self, id: PlaylistId<'_>, edit: F) -> Result<()>
+    where
+        F: FnOnce(&mut Vec<Value>) -> Result<()>,
+    {
+        let mut state = self.state();
+        let playlist = state.content.get_mut(&id.uri()).ok_or(anyhow!("Not found: {}", id.uri()))?;
+        let mut items = playlist["tracks"]["items"].as_array().cloned().unwrap_or_default();
+        edit(&mut items)?;
+        playlist["tracks"] = page(items);
+        Ok(())
+    }
+
+    fn lookup<T: DeserializeOwned>(&self, uri: String) -> Result<T> {
+        parse(self.state().get(&uri)?)
+    }
+}
+
+#[async_trait]
+impl SpotifyBackend for FakePlayer {
+    async fn search(&self, query: &str, _type: SearchType, limit: Option<u32>, offset: Option<u32>) -> Result<SearchResult> {
+        self.call()?;
+        let state = self.state();
+        let query = query.to_lowercase();
+        let type_name = match _type {
+            SearchType::Track => "track",
+            SearchType::Album => "album",
+            SearchType::Playlist => "playlist",
+            SearchType::Artist => "artist",
+            SearchType::Show => "show",
+            SearchType::Episode => "episode",
+        };
+
+        let mut items: Vec<&Value> = state.content.values()
+            .filter(|x| x["type"] == type_name && x["name"].as_str().unwrap_or("").to_lowercase().contains(&query))
+            .collect();
+        // hashmap order isn't stable, so sort by name like a (very simple) relevance
+        items.sort_by_key(|x| x["name"].as_str().unwrap_or("").to_string());
+        let items: Vec<Value> = items.into_iter()
+            .skip(offset.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(20) as usize)
+            .cloned()
+            .collect();
+
+        // searches return the simplified models, which the full json also parses as
+        Ok(match _type {
+            SearchType::Track => SearchResult::Tracks(parse(page(items))?),
+            SearchType::Album => SearchResult::Albums(parse(page(items))?),
+            SearchType::Playlist => SearchResult::Playlists(parse(page(items))?),
+            SearchType::Artist => SearchResult::Artists(parse(page(items))?),
+            SearchType::Show => SearchResult::Shows(parse(page(items))?),
+            SearchType::Episode => SearchResult::Episodes(parse(page(items))?),
+        })
+    }
+
+    async fn start_uris(&self, ids: Vec<PlayableId<'_>>, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        let items: VecDeque<String> = ids.iter().map(|x| x.uri()).collect();
+        for uri in &items {
+            state.get(uri)?;
+        }
+        state.start(device_id, None, items)
+    }
+
+    async fn start_context(&self, id: PlayContextId<'_>, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        let uri = id.uri();
+        let items = state.context_items(&uri)?.into_iter().collect();
+        state.start(device_id, Some(uri), items)
+    }
+
+    async fn resume(&self, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        let device = state.device(device_id)?;
+        state.active_device = Some(device);
+        state.is_playing = state.current.is_some();
+        Ok(())
+    }
+
+    async fn pause(&self, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        state.device(device_id)?;
+        state.is_playing = false;
+        Ok(())
+    }
+
+    async fn next(&self, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        state.device(device_id)?;
+        state.advance();
+        Ok(())
+    }
+
+    async fn previous(&self, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        state.device(device_id)?;
+        state.back();
+        Ok(())
+    }
+
+    async fn seek(&self, position: Duration, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        state.device(device_id)?;
+        state.progress_ms = position.num_milliseconds();
+        Ok(())
+    }
+
+    async fn add_to_queue(&self, id: PlayableId<'_>, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        state.device(device_id)?;
+        let uri = id.uri();
+        state.get(&uri)?;
+        state.queue.push_back(uri);
+        Ok(())
+    }
+
+    async fn volume(&self, level: u8, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        state.device(device_id)?;
+        state.volume = level as u32;
+        Ok(())
+    }
+
+    async fn shuffle(&self, shuffle: bool, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        state.device(device_id)?;
+        state.shuffle = shuffle;
+        Ok(())
+    }
+
+    async fn repeat(&self, repeat: RepeatState, device_id: Option<&str>) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        state.device(device_id)?;
+        state.repeat = repeat;
+        Ok(())
+    }
+
+    async fn devices(&self) -> Result<Vec<Device>> {
+        self.call()?;
+        Ok(self.state().device_list())
+    }
+
+    async fn transfer(&self, device_id: &str) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        let device = state.device(Some(device_id))?;
+        state.active_device = Some(device);
+        Ok(())
+    }
+
+    async fn current_playback(&self) -> Result<Option<CurrentPlaybackContext>> {
+        self.call()?;
+        let state = self.state();
+        let devices = state.device_list();
+        let device = match devices.into_iter().find(|x| x.is_active) {
+            Some(device) => device,
+            None => return Ok(None),
+        };
+
+        let item = match &state.current {
+            Some(uri) => state.get(uri)?,
+            None => Value::Null,
+        };
+        let context = state.context.as_ref().map(|uri| json!({
+            "uri": uri,
+            "href": "",
+            "external_urls": {},
+            "type": uri.split(':').nth(1),
+        }));
+
+        let mut device = serde_json::to_value(device)?;
+        device["volume_percent"] = json!(state.volume);
+
+        Ok(Some(parse(json!({
+            "device": device,
+            "repeat_state": state.repeat,
+            "shuffle_state": state.shuffle,
+            "context": context,
+            "timestamp": 0,
+            "progress_ms": state.progress_ms,
+            "is_playing": state.is_playing,
+            "currently_playing_type": item["type"].as_str().unwrap_or("unknown"),
+            "actions": { "disallows": {} },
+            "item": item,
+        }))?))
+    }
+
+    async fn current_queue(&self) -> Result<CurrentUserQueue> {
+        self.call()?;
+        let state = self.state();
+        let currently_playing = match &state.current {
+            Some(uri) => state.get(uri)?,
+            None => Value::Null,
+        };
+        let queue = state.queue.iter().chain(state.upcoming.iter())
+            .map(|uri| state.get(uri))
+            .collect::<Result<Vec<Value>>>()?;
+
+        parse(json!({
+            "currently_playing": currently_playing,
+            "queue": queue,
+        }))
+    }
+
+    async fn track(&self, id: TrackId<'_>) -> Result<FullTrack> {
+        self.call()?;
+        self.lookup(id.uri())
+    }
+
+    async fn album(&self, id: AlbumId<'_>) -> Result<FullAlbum> {
+        self.call()?;
+        self.lookup(id.uri())
+    }
+
+    async fn playlist(&self, id: PlaylistId<'_>) -> Result<FullPlaylist> {
+        self.call()?;
+        self.lookup(id.uri())
+    }
+
+    async fn artist(&self, id: ArtistId<'_>) -> Result<FullArtist> {
+        self.call()?;
+        self.lookup(id.uri())
+    }
+
+    async fn show(&self, id: ShowId<'_>) -> Result<FullShow> {
+        self.call()?;
+        self.lookup(id.uri())
+    }
+
+    async fn episode(&self, id: EpisodeId<'_>) -> Result<FullEpisode> {
+        self.call()?;
+        self.lookup(id.uri())
+    }
+
+    async fn album_tracks(&self, id: AlbumId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedTrack>> {
+        self.call()?;
+        let album = self.state().get(&id.uri())?;
+        let items = album["tracks"]["items"].as_array().cloned().unwrap_or_default();
+        parse(offset_page(items, limit, offset, 20))
+    }
+
+    async fn show_episodes(&self, id: ShowId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedEpisode>> {
+        self.call()?;
+        let show = self.state().get(&id.uri())?;
+        let items = show["episodes"]["items"].as_array().cloned().unwrap_or_default();
+        parse(offset_page(items, limit, offset, 20))
+    }
+
+    async fn save_tracks(&self, ids: Vec<TrackId<'_>>) -> Result<()> {
+        self.call()?;
+        for id in ids {
+            self.state().get(&id.uri())?;
+            self.save(&id.uri());
+        }
+        Ok(())
+    }
+
+    async fn unsave_tracks(&self, ids: Vec<TrackId<'_>>) -> Result<()> {
+        self.call()?;
+        let uris: Vec<String> = ids.iter().map(|x| x.uri()).collect();
+        self.state().saved.retain(|x| !uris.contains(x));
+        Ok(())
+    }
+
+    async fn saved_tracks(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SavedTrack>> {
+        self.call()?;
+        self.saved_page("track", limit, offset)
+    }
+
+    async fn saved_albums(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SavedAlbum>> {
+        self.call()?;
+        self.saved_page("album", limit, offset)
+    }
+
+    async fn saved_shows(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<Show>> {
+        self.call()?;
+        self.saved_page("show", limit, offset)
+    }
+
+    async fn my_playlists(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedPlaylist>> {
+        self.call()?;
+        let state = self.state();
+        let mut playlists: Vec<Value> = state.content.values().filter(|x| x["type"] == "playlist").cloned().collect();
+        playlists.sort_by_key(|x| x["name"].as_str().unwrap_or("").to_string());
+        parse(offset_page(playlists, limit, offset, 20))
+    }
+
+    async fn playlist_items(&self, id: PlaylistId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<PlaylistItem>> {
+        self.call()?;
+        let playlist = self.state().get(&id.uri())?;
+        let items = playlist["tracks"]["items"].as_array().cloned().unwrap_or_default();
+        parse(offset_page(items, limit, offset, 100))
+    }
+
+    async fn playlist_create(&self, name: &str, public: bool) -> Result<FullPlaylist> {
+        self.call()?;
+        let uri = self.add_playlist(name, &[]);
+        self.state().content.get_mut(&uri).unwrap()["public"] = json!(public);
+        self.lookup(uri)
+    }
+
+    async fn playlist_add(&self, id: PlaylistId<'_>, items: Vec<PlayableId<'_>>) -> Result<()> {
+        self.call()?;
+        let tracks = items.iter().map(|x| self.state().get(&x.uri())).collect::<Result<Vec<Value>>>()?;
+        self.edit_playlist(id, |items| {
+            items.extend(tracks.into_iter().map(playlist_item));
+            Ok(())
+        })
+    }
+
+    // snapshots aren't tracked, but like the web api the item has to be at the position
+    async fn playlist_remove(&self, id: PlaylistId<'_>, item: PlayableId<'_>, position: u32, _snapshot_id: &str) -> Result<()> {
+        self.call()?;
+        let uri = item.uri();
+        self.edit_playlist(id, |items| {
+            match items.get(position as usize) {
+                Some(x) if x["track"]["uri"] == uri.as_str() => {
+                    items.remove(position as usize);
+                    Ok(())
+                },
+                _ => Err(anyhow!("Invalid position")),
+            }
+        })
+    }
+
+    async fn playlist_reorder(&self, id: PlaylistId<'_>, range_start: u32, insert_before: u32) -> Result<()> {
+        self.call()?;
+        self.edit_playlist(id, |items| {
+            let (from, before) = (range_start as usize, insert_before as usize);
+            if from >= items.len() || before > items.len() {
+                return Err(anyhow!("Invalid position"));
+            }
+            let item = items.remove(from);
+            // insert_before counts the item being moved
+            items.insert(if before > from { before - 1 } else { before }, item);
+            Ok(())
+        })
+    }
+
+    async fn playlist_rename(&self, id: PlaylistId<'_>, name: &str) -> Result<()> {
+        self.call()?;
+        let mut state = self.state();
+        let playlist = state.content.get_mut(&id.uri()).ok_or(anyhow!("Not found: {}", id.uri()))?;
+        playlist["name"] = json!(name);
+        Ok(())
+    }
+
+    // the fake player doesn't check tokens
+    async fn refresh_token(&self) -> Result<()> {
+        self.call()?;
+        Ok(())
+    }
+}
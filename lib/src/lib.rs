@@ -1,30 +1,77 @@
+pub mod auth;
 pub mod config;
+pub mod error;
 pub mod spotify;
 pub mod model;
+pub mod clock;
+pub mod response;
+pub mod backend;
+pub mod retry;
+pub mod device;
+pub mod pages;
+// an in-memory player for tests and the fake api, not needed by the front-ends
+#[cfg(any(test, feature = "fake"))]
+pub mod fake;
 
-use config::ClientConfig;
+use backend::SpotifyBackend;
+use config::{ AuthMode, ClientConfig, };
 
 use anyhow::Result;
 use rspotify::{
-    AuthCodeSpotify, Credentials, OAuth, Config,
+    AuthCodeSpotify, AuthCodePkceSpotify, Credentials, OAuth, Config,
     scopes,
-    clients::{ OAuthClient, BaseClient, },
 };
 
-// init with sensible defaults, if you want more control do it manually
-// AuthCodeSpotify must be used, OAuthClient is Sized and therefore cannot be a trait object, so i gotta use a real type
-pub async fn spotify_init() -> Result<AuthCodeSpotify> {
+// init with sensible defaults, if you want more control do it manually.
+// logs in with a client secret or with pkce, whichever `auth` in client.yml says
+pub async fn spotify_init() -> Result<Box<dyn SpotifyBackend>> {
     let mut client_config = ClientConfig::new();
     client_config.load_config()?;
 
-    let config_paths = client_config.get_or_build_paths()?;
+    let port = client_config.get_port();
+    let pending_login_path = client_config.get_or_build_paths()?.pending_login_path;
+    match client_config.auth {
+        AuthMode::Code => {
+            let mut spotify = spotify_client(&client_config)?;
+            auth::login(&mut spotify, port, &pending_login_path).await?;
+            return Ok(Box::new(spotify));
+        },
+        AuthMode::Pkce => {
+            let mut spotify = spotify_pkce_client(&client_config)?;
+            auth::login(&mut spotify, port, &pending_login_path).await?;
+            return Ok(Box::new(spotify));
+        },
+    }
+}
 
+// a client that isn't logged in yet, see auth for logging in
+pub fn spotify_client(client_config: &ClientConfig) -> Result<AuthCodeSpotify> {
     let creds = Credentials::new(&client_config.client_id, &client_config.client_secret);
+    let (oauth, config) = client_options(client_config)?;
+    return Ok(AuthCodeSpotify::with_config(creds, oauth, config));
+}
+
+// the same, but without the client secret
+pub fn spotify_pkce_client(client_config: &ClientConfig) -> Result<AuthCodePkceSpotify> {
+    let creds = Credentials::new_pkce(&client_config.client_id);
+    let (oauth, config) = client_options(client_config)?;
+    return Ok(AuthCodePkceSpotify::with_config(creds, oauth, config));
+}
+
+fn client_options(client_config: &ClientConfig) -> Result<(OAuth, Config)> {
+    let config_paths = client_config.get_or_build_paths()?;
+
     let oauth = OAuth {
         redirect_uri: client_config.get_redirect_uri(),
         scopes: scopes!(
             "user-modify-playback-state", 
-            "user-read-playback-state"
+            "user-read-playback-state",
+            "user-library-read",
+            "user-library-modify",
+            "playlist-read-private",
+            "playlist-read-collaborative",
+            "playlist-modify-public",
+            "playlist-modify-private"
         ),
         ..Default::default()
     };
@@ -32,16 +79,9 @@ pub async fn spotify_init() -> Result<AuthCodeSpotify> {
         token_refreshing: true,
         token_cached: true,
         cache_path: config_paths.token_cache_path,
+        prefix: client_config.get_api_url(),
         ..Default::default()
     };
 
-    let spotify = AuthCodeSpotify::with_config(creds, oauth, config);
-
-    spotify.refresh_token().await?;
-    if spotify.get_token().lock().await.unwrap().is_none() {
-        let url = spotify.get_authorize_url(false).unwrap();
-        spotify.prompt_for_token(&url).await.unwrap();
-    }
-
-    return Ok(spotify);
+    return Ok((oauth, config));
 }
@@ -1,5 +1,13 @@
+use crate::error::FishifyError;
+
 use anyhow::{ /* anyhow ,*/ Result, };
 
+use std::{
+    fmt,
+    collections::HashMap,
+};
+
+use serde::Serialize;
 use time::Duration;
 
 use rspotify::{
@@ -15,8 +23,10 @@ use rspotify::{
             IdError, 
         },
         search::SearchResult,
+        image::Image,
         PlayableItem,
     },
+    prelude::Id,
 };
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -35,8 +45,66 @@ pub enum ContentType {
     FullEpisode(FullEpisode),
 }
 
+// why an item couldn't be played or queued
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    // local files are in playlists, but spotify has no id for them
+    LocalFile,
+    // removed, or not available in the user's market
+    Unavailable,
+    // albums, artists and the like, which can't go in a queue or playlist
+    NotPlayable,
+}
+
+impl SkipReason {
+    // e.g. "3 local files"
+    pub fn describe(&self, count: usize) -> String {
+        let s = if count == 1 { "" } else { "s" };
+        match self {
+            Self::LocalFile => format!("{count} local file{s}"),
+            Self::Unavailable => format!("{count} unavailable item{s}"),
+            Self::NotPlayable => format!("{count} item{s} that can't be played"),
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::LocalFile => write!(f, "Local files can't be played by fishify"),
+            Self::Unavailable => write!(f, "Item is unavailable"),
+            Self::NotPlayable => write!(f, "Only tracks and episodes can be played this way"),
+        }
+    }
+}
+
+impl std::error::Error for SkipReason {}
+
+// the playable ids out of a list of items, and why the rest were skipped
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Playables<'a> {
+    pub ids: Vec<PlayableId<'a>>,
+    pub skipped: Vec<SkipReason>,
+}
+
+impl Playables<'static> {
+    // None is an item the api couldn't return, like a removed track in a playlist
+    pub fn from_items(items: impl IntoIterator<Item = Option<ContentType>>) -> Self {
+        let mut playables = Self::default();
+        for item in items {
+            match item.ok_or(SkipReason::Unavailable).and_then(|x| x.playable_id()) {
+                Ok(id) => playables.ids.push(id),
+                Err(reason) => playables.skipped.push(reason),
+            }
+        }
+        playables
+    }
+}
+
 impl ContentType {
-    pub fn ids(&self) -> Option<Vec<PlayableId>> {
+    // only the items on the first page of a context, pages::ContextPages fetches all of them
+    pub fn ids(&self) -> Option<Playables<'static>> {
         match self {
             Self::SimplifiedTrack(_item) => None,
             Self::SimplifiedAlbum(_item) => None,
@@ -45,13 +113,36 @@ impl ContentType {
             Self::SimplifiedShow(_item) => None,
             Self::SimplifiedEpisode(_item) => None,
             Self::FullTrack(_item) => None,
-            Self::FullAlbum(item) => Some(item.tracks.items.iter().map(|x| PlayableId::from(x.id.clone().expect("missing id"))).collect()),
-            Self::FullPlaylist(item) => Some(item.tracks.items.iter().map(|x| x.track.as_ref().expect("missing track").id().expect("missing id")).collect()),
+            Self::FullAlbum(item) => Some(Playables::from_items(item.tracks.items.iter().map(|x| Some(Self::from(x.clone()))))),
+            Self::FullPlaylist(item) => Some(Playables::from_items(item.tracks.items.iter().map(|x| x.track.clone().map(Self::from)))),
             Self::FullArtist(_item) => None,
-            Self::FullShow(item) => Some(item.episodes.items.iter().map(|x| PlayableId::from(x.id.clone())).collect()),
+            Self::FullShow(item) => Some(Playables::from_items(item.episodes.items.iter().map(|x| Some(Self::from(x.clone()))))),
             Self::FullEpisode(_item) => None,
         }
     }
+
+    // the id of a track or episode, if it can be played
+    pub fn playable_id(&self) -> std::result::Result<PlayableId<'static>, SkipReason> {
+        match self {
+            Self::SimplifiedTrack(item) => track_id(item.is_local, item.is_playable, &item.id),
+            Self::FullTrack(item) => track_id(item.is_local, item.is_playable, &item.id),
+            Self::SimplifiedEpisode(item) if item.is_playable => Ok(PlayableId::from(item.id.clone())),
+            Self::FullEpisode(item) if item.is_playable => Ok(PlayableId::from(item.id.clone())),
+            Self::SimplifiedEpisode(_item) | Self::FullEpisode(_item) => Err(SkipReason::Unavailable),
+            _ => Err(SkipReason::NotPlayable),
+        }
+    }
+}
+
+fn track_id(is_local: bool, is_playable: Option<bool>, id: &Option<TrackId<'static>>) -> std::result::Result<PlayableId<'static>, SkipReason> {
+    if is_local {
+        return Err(SkipReason::LocalFile);
+    }
+    if is_playable == Some(false) {
+        return Err(SkipReason::Unavailable);
+    }
+    // only local files are missing ids
+    id.clone().map(PlayableId::from).ok_or(SkipReason::LocalFile)
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -66,16 +157,38 @@ pub enum ContentId<'a> {
 
 impl<'a> ContentId<'a> {
     pub fn from_uri(uri: &'a str) -> Result<Self> {
-        let _type = uri.split(':').nth(1).ok_or(IdError::InvalidFormat)?;
+        let invalid = |_| FishifyError::InvalidUri(uri.to_string());
+        let _type = uri.split(':').nth(1).ok_or(IdError::InvalidFormat).map_err(invalid)?;
         
-        match _type {
-            "track" => Ok(ContentId::from(TrackId::from_uri(uri)?)),
-            "album" => Ok(ContentId::from(AlbumId::from_uri(uri)?)),
-            "playlist" => Ok(ContentId::from(PlaylistId::from_uri(uri)?)),
-            "artist" => Ok(ContentId::from(ArtistId::from_uri(uri)?)),
-            "show" => Ok(ContentId::from(ShowId::from_uri(uri)?)),
-            "episode" => Ok(ContentId::from(EpisodeId::from_uri(uri)?)),
-            _ => Err(IdError::InvalidType.into()),
+        let id = match _type {
+            "track" => TrackId::from_uri(uri).map(ContentId::from),
+            "album" => AlbumId::from_uri(uri).map(ContentId::from),
+            "playlist" => PlaylistId::from_uri(uri).map(ContentId::from),
+            "artist" => ArtistId::from_uri(uri).map(ContentId::from),
+            "show" => ShowId::from_uri(uri).map(ContentId::from),
+            "episode" => EpisodeId::from_uri(uri).map(ContentId::from),
+            _ => Err(IdError::InvalidType),
+        };
+        Ok(id.map_err(invalid)?)
+    }
+
+    pub fn into_static(self) -> ContentId<'static> {
+        match self {
+            Self::Track(id) => ContentId::Track(id.into_static()),
+            Self::Album(id) => ContentId::Album(id.into_static()),
+            Self::Playlist(id) => ContentId::Playlist(id.into_static()),
+            Self::Artist(id) => ContentId::Artist(id.into_static()),
+            Self::Show(id) => ContentId::Show(id.into_static()),
+            Self::Episode(id) => ContentId::Episode(id.into_static()),
+        }
+    }
+
+    // tracks and episodes, the ids that can go in a queue or playlist
+    pub fn playable(self) -> Option<PlayableId<'a>> {
+        match self {
+            Self::Track(id) => Some(PlayableId::from(id)),
+            Self::Episode(id) => Some(PlayableId::from(id)),
+            _ => None,
         }
     }
 }
@@ -99,7 +212,8 @@ impl FromSearch for ContentType {
 
 impl<'a> FromSearch for ContentId<'a> {
     fn from_search(result: SearchResult) -> Box<dyn Iterator<Item = Self>> {
-        Box::new(ContentType::from_search(result).map(|x| Self::from(x)))
+        // search results always have ids, but skip any that don't rather than panic
+        Box::new(ContentType::from_search(result).filter_map(|x| Self::try_from(x).ok()))
     }
 }
 
@@ -110,6 +224,20 @@ pub trait ContentInfo {
         self.artists().get(0).cloned()
     }
     fn duration(&self) -> Option<Duration>;
+    fn uri(&self) -> Option<String>;
+    // the open.spotify.com link
+    fn url(&self) -> Option<String>;
+    // the largest cover art, album art for tracks
+    fn image(&self) -> Option<String>;
+}
+
+fn spotify_url(external_urls: &HashMap<String, String>) -> Option<String> {
+    external_urls.get("spotify").cloned()
+}
+
+// spotify lists images largest first
+fn largest_image(images: &[Image]) -> Option<String> {
+    images.first().map(|x| x.url.clone())
 }
 
 impl ContentInfo for ContentType {
@@ -169,6 +297,56 @@ impl ContentInfo for ContentType {
         }
     }
 
+    fn uri(&self) -> Option<String> {
+        match self {
+            Self::SimplifiedTrack(item) => item.id.as_ref().map(|id| id.uri()),
+            Self::SimplifiedAlbum(item) => item.id.as_ref().map(|id| id.uri()),
+            Self::SimplifiedPlaylist(item) => Some(item.id.uri()),
+            Self::SimplifiedArtist(item) => item.id.as_ref().map(|id| id.uri()),
+            Self::SimplifiedShow(item) => Some(item.id.uri()),
+            Self::SimplifiedEpisode(item) => Some(item.id.uri()),
+            Self::FullTrack(item) => item.id.as_ref().map(|id| id.uri()),
+            Self::FullAlbum(item) => Some(item.id.uri()),
+            Self::FullPlaylist(item) => Some(item.id.uri()),
+            Self::FullArtist(item) => Some(item.id.uri()),
+            Self::FullShow(item) => Some(item.id.uri()),
+            Self::FullEpisode(item) => Some(item.id.uri()),
+        }
+    }
+
+    fn url(&self) -> Option<String> {
+        match self {
+            Self::SimplifiedTrack(item) => spotify_url(&item.external_urls),
+            Self::SimplifiedAlbum(item) => spotify_url(&item.external_urls),
+            Self::SimplifiedPlaylist(item) => spotify_url(&item.external_urls),
+            Self::SimplifiedArtist(item) => spotify_url(&item.external_urls),
+            Self::SimplifiedShow(item) => spotify_url(&item.external_urls),
+            Self::SimplifiedEpisode(item) => spotify_url(&item.external_urls),
+            Self::FullTrack(item) => spotify_url(&item.external_urls),
+            Self::FullAlbum(item) => spotify_url(&item.external_urls),
+            Self::FullPlaylist(item) => spotify_url(&item.external_urls),
+            Self::FullArtist(item) => spotify_url(&item.external_urls),
+            Self::FullShow(item) => spotify_url(&item.external_urls),
+            Self::FullEpisode(item) => spotify_url(&item.external_urls),
+        }
+    }
+
+    fn image(&self) -> Option<String> {
+        match self {
+            Self::SimplifiedTrack(_item) => None,
+            Self::SimplifiedAlbum(item) => largest_image(&item.images),
+            Self::SimplifiedPlaylist(item) => largest_image(&item.images),
+            Self::SimplifiedArtist(_item) => None,
+            Self::SimplifiedShow(item) => largest_image(&item.images),
+            Self::SimplifiedEpisode(item) => largest_image(&item.images),
+            Self::FullTrack(item) => largest_image(&item.album.images),
+            Self::FullAlbum(item) => largest_image(&item.images),
+            Self::FullPlaylist(item) => largest_image(&item.images),
+            Self::FullArtist(item) => largest_image(&item.images),
+            Self::FullShow(item) => largest_image(&item.images),
+            Self::FullEpisode(item) => largest_image(&item.images),
+        }
+    }
 }
 
 impl ContentInfo for PlayableItem {
@@ -192,6 +370,27 @@ impl ContentInfo for PlayableItem {
             Self::Episode(item) => Some(item.duration.clone()),
         }
     }
+
+    fn uri(&self) -> Option<String> {
+        match self {
+            Self::Track(item) => item.id.as_ref().map(|id| id.uri()),
+            Self::Episode(item) => Some(item.id.uri()),
+        }
+    }
+
+    fn url(&self) -> Option<String> {
+        match self {
+            Self::Track(item) => spotify_url(&item.external_urls),
+            Self::Episode(item) => spotify_url(&item.external_urls),
+        }
+    }
+
+    fn image(&self) -> Option<String> {
+        match self {
+            Self::Track(item) => largest_image(&item.album.images),
+            Self::Episode(item) => largest_image(&item.images),
+        }
+    }
 }
 
 // Froms
@@ -252,21 +451,33 @@ impl<'a> From<PlayContextId<'a>> for ContentId<'a> {
     }
 }
 
-impl From<ContentType> for ContentId<'_> {
-    fn from(item: ContentType) -> Self {
+// only local content is missing an id
+impl TryFrom<ContentType> for ContentId<'_> {
+    type Error = SkipReason;
+
+    fn try_from(item: ContentType) -> std::result::Result<Self, SkipReason> {
+        match item {
+            ContentType::SimplifiedTrack(item) => item.id.map(ContentId::from).ok_or(SkipReason::LocalFile),
+            ContentType::SimplifiedAlbum(item) => item.id.map(ContentId::from).ok_or(SkipReason::LocalFile),
+            ContentType::SimplifiedPlaylist(item) => Ok(ContentId::from(item.id)),
+            ContentType::SimplifiedArtist(item) => item.id.map(ContentId::from).ok_or(SkipReason::LocalFile),
+            ContentType::SimplifiedShow(item) => Ok(ContentId::from(item.id)),
+            ContentType::SimplifiedEpisode(item) => Ok(ContentId::from(item.id)),
+            ContentType::FullTrack(item) => item.id.map(ContentId::from).ok_or(SkipReason::LocalFile),
+            ContentType::FullAlbum(item) => Ok(ContentId::from(item.id)),
+            ContentType::FullPlaylist(item) => Ok(ContentId::from(item.id)),
+            ContentType::FullArtist(item) => Ok(ContentId::from(item.id)),
+            ContentType::FullShow(item) => Ok(ContentId::from(item.id)),
+            ContentType::FullEpisode(item) => Ok(ContentId::from(item.id)),
+        }
+    }
+}
+
+impl From<PlayableItem> for ContentType {
+    fn from(item: PlayableItem) -> Self {
         match item {
-            ContentType::SimplifiedTrack(item) => ContentId::from(item.id.clone().expect("missing id")),
-            ContentType::SimplifiedAlbum(item) => ContentId::from(item.id.clone().expect("missing id")),
-            ContentType::SimplifiedPlaylist(item) => ContentId::from(item.id.clone()),
-            ContentType::SimplifiedArtist(item) => ContentId::from(item.id.clone().expect("missing id")),
-            ContentType::SimplifiedShow(item) => ContentId::from(item.id.clone()),
-            ContentType::SimplifiedEpisode(item) => ContentId::from(item.id.clone()),
-            ContentType::FullTrack(item) => ContentId::from(item.id.clone().expect("missing id")),
-            ContentType::FullAlbum(item) => ContentId::from(item.id.clone()),
-            ContentType::FullPlaylist(item) => ContentId::from(item.id.clone()),
-            ContentType::FullArtist(item) => ContentId::from(item.id.clone()),
-            ContentType::FullShow(item) => ContentId::from(item.id.clone()),
-            ContentType::FullEpisode(item) => ContentId::from(item.id.clone()),
+            PlayableItem::Track(item) => Self::FullTrack(item),
+            PlayableItem::Episode(item) => Self::FullEpisode(item),
         }
     }
 }
@@ -0,0 +1,155 @@
+use crate::{
+    backend::SpotifyBackend,
+    error::FishifyError,
+    model::{ ContentType, ContentId, },
+};
+
+use std::{
+    fmt,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+// the most items the web api returns in one page
+const ALBUM_PAGE_LIMIT: u32 = 50;
+const PLAYLIST_PAGE_LIMIT: u32 = 100;
+const SHOW_PAGE_LIMIT: u32 = 50;
+
+// contexts smaller than this finish quickly, so they don't report progress
+const PROGRESS_THRESHOLD: u32 = 100;
+// how often queueing reports progress, in items
+const PROGRESS_STEP: u32 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum Progress {
+    Fetched { done: u32, total: u32 },
+    Queued { done: u32, total: u32 },
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Fetched { done, total } => write!(f, "Fetched {done}/{total} items"),
+            Self::Queued { done, total } => write!(f, "Queued {done}/{total} items"),
+        }
+    }
+}
+
+// how much of a context to fetch, and where to report progress
+#[derive(Clone)]
+pub struct PageOptions {
+    // None fetches every item
+    pub max_items: Option<u32>,
+    pub progress: Arc<dyn Fn(Progress) + Send + Sync>,
+}
+
+impl PageOptions {
+    pub(crate) fn report(&self, progress: Progress) {
+        let (done, total) = match progress {
+            Progress::Fetched { done, total } => (done, total),
+            Progress::Queued { done, total } => (done, total),
+        };
+        if total >= PROGRESS_THRESHOLD && (done % PROGRESS_STEP == 0 || done == total) {
+            (self.progress)(progress);
+        }
+    }
+}
+
+impl Default for PageOptions {
+    fn default() -> Self {
+        return Self {
+            max_items: None,
+            progress: Arc::new(|_| ()),
+        };
+    }
+}
+
+// Pages through the items of an album, playlist or show, past the first page that
+// `FishifyClient::get_content` returns. Unavailable playlist items are None, so positions
+// still line up with the playlist.
+pub struct ContextPages<'a, B: SpotifyBackend + ?Sized> {
+    spotify: &'a B,
+    id: ContentId<'a>,
+    offset: u32,
+    // known after the first page
+    total: Option<u32>,
+    max_items: Option<u32>,
+}
+
+impl<'a, B: SpotifyBackend + ?Sized> ContextPages<'a, B> {
+    pub fn new(spotify: &'a B, id: ContentId<'a>, max_items: Option<u32>) -> Result<Self> {
+        match id {
+            ContentId::Album(_) | ContentId::Playlist(_) | ContentId::Show(_) => Ok(Self {
+                spotify,
+                id,
+                offset: 0,
+                total: None,
+                max_items,
+            }),
+            _ => Err(FishifyError::NotAContext.into()),
+        }
+    }
+
+    // every item in the context, even those past max_items
+    pub fn available(&self) -> Option<u32> {
+        self.total
+    }
+
+    // the number of items that will be fetched
+    pub fn total(&self) -> Option<u32> {
+        self.total.map(|total| self.max_items.map_or(total, |max| total.min(max)))
+    }
+
+    pub fn is_capped(&self) -> bool {
+        matches!((self.total, self.max_items), (Some(total), Some(max)) if total > max)
+    }
+
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Option<ContentType>>>> {
+        let remaining = match self.total() {
+            Some(total) => total.saturating_sub(self.offset),
+            None => self.max_items.unwrap_or(u32::MAX),
+        };
+        if remaining == 0 {
+            return Ok(None);
+        }
+
+        let offset = Some(self.offset);
+        let (items, total): (Vec<Option<ContentType>>, u32) = match &self.id {
+            ContentId::Album(id) => {
+                let page = self.spotify.album_tracks(id.clone(), Some(remaining.min(ALBUM_PAGE_LIMIT)), offset).await?;
+                (page.items.into_iter().map(|x| Some(ContentType::from(x))).collect(), page.total)
+            },
+            ContentId::Playlist(id) => {
+                let page = self.spotify.playlist_items(id.clone(), Some(remaining.min(PLAYLIST_PAGE_LIMIT)), offset).await?;
+                (page.items.into_iter().map(|x| x.track.map(ContentType::from)).collect(), page.total)
+            },
+            ContentId::Show(id) => {
+                let page = self.spotify.show_episodes(id.clone(), Some(remaining.min(SHOW_PAGE_LIMIT)), offset).await?;
+                (page.items.into_iter().map(|x| Some(ContentType::from(x))).collect(), page.total)
+            },
+            _ => unreachable!("checked in new"),
+        };
+
+        self.total = Some(total);
+        // the context may have shrunk since the total was read
+        if items.is_empty() {
+            self.offset = total;
+            return Ok(None);
+        }
+        self.offset += items.len() as u32;
+        Ok(Some(items))
+    }
+
+    // fetch every remaining page
+    pub async fn collect(&mut self, options: &PageOptions) -> Result<Vec<Option<ContentType>>> {
+        let mut items = vec![];
+        while let Some(page) = self.next_page().await? {
+            items.extend(page);
+            options.report(Progress::Fetched { done: self.offset, total: self.total().unwrap_or(self.offset) });
+        }
+        Ok(items)
+    }
+}
@@ -0,0 +1,682 @@
+use crate::{
+    model::{ ContentInfo, SkipReason, },
+    clock::duration_clock_format,
+};
+
+use std::{
+    fmt,
+    collections::BTreeMap,
+};
+
+use serde::{ Serialize, Serializer, };
+use time::Duration;
+
+use rspotify::model::{
+    device::Device,
+    enums::{
+        types::{ Type, DeviceType, },
+        misc::{ RepeatState, },
+    },
+};
+
+// durations are written as milliseconds, like the spotify api does
+fn serialize_ms<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.map(|x| x.num_milliseconds()).serialize(serializer)
+}
+
+fn serialize_position_ms<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.num_milliseconds().serialize(serializer)
+}
+
+// a flattened view of anything implementing ContentInfo, so front-ends don't need rspotify's models
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Item {
+    pub name: String,
+    pub artist: Option<String>,
+    pub uri: Option<String>,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_ms")]
+    pub duration: Option<Duration>,
+    // open.spotify.com links, and cover art
+    pub url: Option<String>,
+    pub artist_url: Option<String>,
+    pub image: Option<String>,
+}
+
+impl<T: ContentInfo> From<&T> for Item {
+    fn from(item: &T) -> Self {
+        return Self {
+            name: item.name(),
+            artist: item.artist().map(|x| x.name),
+            uri: item.uri(),
+            duration: item.duration(),
+            url: item.url(),
+            artist_url: item.artist().and_then(|x| x.external_urls.get("spotify").cloned()),
+            image: item.image(),
+        };
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.artist {
+            Some(artist) => write!(f, "{} \u{2014} {}", self.name, artist),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// how many items were left out of a context, by reason, e.g. {"local_file": 3}
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Skipped(pub BTreeMap<SkipReason, usize>);
+
+impl Skipped {
+    pub fn count(&self) -> usize {
+        self.0.values().sum()
+    }
+}
+
+impl From<&[SkipReason]> for Skipped {
+    fn from(reasons: &[SkipReason]) -> Self {
+        let mut skipped = BTreeMap::new();
+        for reason in reasons {
+            *skipped.entry(*reason).or_insert(0) += 1;
+        }
+        return Self(skipped);
+    }
+}
+
+// e.g. "3 local files and 1 unavailable item"
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|(reason, count)| reason.describe(*count)).collect();
+        match parts.split_last() {
+            Some((last, [])) => write!(f, "{last}"),
+            Some((last, rest)) => write!(f, "{} and {last}", rest.join(", ")),
+            None => write!(f, "nothing"),
+        }
+    }
+}
+
+// result of anything that changes playback instead of reading it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
+pub enum Action {
+    Resumed,
+    Playing(Item),
+    Queued(Item),
+    // an album, playlist or show, queued one item at a time
+    QueuedContext { item: Item, queued: usize, skipped: Skipped },
+    PlayingLiked(usize),
+    Liked(Item),
+    Unliked(Item),
+    PlaylistCreated(String),
+    PlaylistAdded { playlist: String, item: Item },
+    PlaylistRemoved { playlist: String, item: Item },
+    PlaylistMoved { playlist: String, item: Item, position: u32 },
+    PlaylistRenamed { playlist: String, name: String },
+    Paused,
+    Skipped(u8),
+    Previous,
+    Seeked(#[serde(serialize_with = "serialize_position_ms")] Duration),
+    Restarted,
+    Connected(String),
+    Volume(u8),
+    Shuffle(bool),
+    Repeat(RepeatState),
+}
+
+impl Action {
+    // whether this is worth highlighting, or just a confirmation
+    pub fn show(&self) -> bool {
+        matches!(self, Self::Playing(_) | Self::Queued(_) | Self::QueuedContext { .. } | Self::PlayingLiked(_) | Self::Liked(_) | Self::Unliked(_))
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (prefix, item) = match self {
+            Self::Resumed => return write!(f, "Resumed playback"),
+            Self::Paused => return write!(f, "Paused playback"),
+            Self::Skipped(count) => return write!(f, "Skipped {count} tracks"),
+            Self::Previous => return write!(f, "Went back to the previous track"),
+            Self::Seeked(position) => return write!(f, "Seeked to {}", duration_clock_format(*position)),
+            Self::Restarted => return write!(f, "Restarted track"),
+            Self::PlayingLiked(count) => return write!(f, "Now playing {count} liked songs"),
+            Self::QueuedContext { item, queued, skipped } => {
+                write!(f, "Queued {queued} items from {}", item.name)?;
+                if let Some(artist) = &item.artist {
+                    write!(f, " by {artist}")?;
+                }
+                return match skipped.count() {
+                    0 => Ok(()),
+                    1 => write!(f, "\n{skipped} was not queued"),
+                    _ => write!(f, "\n{skipped} were not queued"),
+                };
+            },
+            Self::PlaylistCreated(name) => return write!(f, "Created playlist {name}"),
+            Self::PlaylistAdded { playlist, item } => return write!(f, "Added {} to {playlist}", item.name),
+            Self::PlaylistRemoved { playlist, item } => return write!(f, "Removed {} from {playlist}", item.name),
+            Self::PlaylistMoved { playlist, item, position } => return write!(f, "Moved {} to position {position} in {playlist}", item.name),
+            Self::PlaylistRenamed { playlist, name } => return write!(f, "Renamed {playlist} to {name}"),
+            Self::Liked(item) => ("Liked", item),
+            Self::Unliked(item) => ("Unliked", item),
+            Self::Connected(name) => return write!(f, "Connected to {name}"),
+            Self::Volume(level) => return write!(f, "Set volume to {level}"),
+            Self::Shuffle(state) => return write!(f, "Set shuffle to {state}"),
+            Self::Repeat(state) => return write!(f, "Set repeat to {state:?}"),
+            Self::Playing(item) => ("Now playing", item),
+            Self::Queued(item) => ("Queued", item),
+        };
+
+        match &item.artist {
+            Some(artist) => write!(f, "{prefix} {} by {artist}", item.name),
+            None => write!(f, "{prefix} {}", item.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaybackContext {
+    #[serde(rename = "type")]
+    pub _type: Type,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaybackStatus {
+    pub is_playing: bool,
+    pub context: Option<PlaybackContext>,
+    pub item: Option<Item>,
+    #[serde(rename = "progress_ms", serialize_with = "serialize_ms")]
+    pub progress: Option<Duration>,
+    pub volume: Option<u32>,
+    pub shuffle: bool,
+    pub repeat: RepeatState,
+}
+
+impl fmt::Display for PlaybackStatus {
+    // This will create a message with the format:
+    //   {is_playing}
+    //   {_type} {type_name}
+    //   {name} --- {artist}
+    //   {progress} / {duration}
+    //   Volume: {volume}%
+    //   Shuffle: {shuffle}
+    //   Repeat: {repeat}
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_playing {
+            writeln!(f, "Playing")?;
+        } else {
+            writeln!(f, "Paused")?;
+        }
+
+        if let Some(context) = &self.context {
+            writeln!(f, "{:?}: {}", context._type, context.name)?;
+        }
+
+        if let Some(item) = &self.item {
+            writeln!(f, "{item}")?;
+
+            if let (Some(progress), Some(duration)) = (self.progress, item.duration) {
+                writeln!(f, "{} / {}", duration_clock_format(progress), duration_clock_format(duration))?;
+            }
+        }
+
+        if let Some(volume) = self.volume {
+            writeln!(f, "Volume: {volume}%")?;
+        }
+
+        let shuffle_state = if self.shuffle {
+            "On"
+        } else {
+            "Off"
+        };
+        writeln!(f, "Shuffle: {shuffle_state}")?;
+
+        write!(f, "Repeat: {:?}", self.repeat)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueueListing {
+    pub currently_playing: Option<Item>,
+    pub queue: Vec<Item>,
+}
+
+impl fmt::Display for QueueListing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut lines = vec![];
+
+        if let Some(item) = &self.currently_playing {
+            match &item.artist {
+                Some(artist) => lines.push(format!("Currently playing {} by {artist}", item.name)),
+                None => lines.push(format!("Currently playing {}", item.name)),
+            }
+        }
+
+        for (i, item) in self.queue.iter().enumerate() {
+            let index = i+1;
+            lines.push(format!("{index:>3}. {item}"));
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResults {
+    pub items: Vec<Item>,
+}
+
+impl fmt::Display for SearchResults {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self.items.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaylistContents {
+    pub name: String,
+    pub uri: String,
+    pub items: Vec<Item>,
+    pub total: u32,
+}
+
+impl fmt::Display for PlaylistContents {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut lines = vec![self.name.clone()];
+
+        for (i, item) in self.items.iter().enumerate() {
+            let index = i+1;
+            lines.push(format!("{index:>3}. {item}"));
+        }
+
+        if (self.items.len() as u32) < self.total {
+            lines.push(format!("...and {} more", self.total - self.items.len() as u32));
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+// saved tracks, albums, shows or playlists
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Library {
+    pub items: Vec<Item>,
+    pub total: u32,
+}
+
+impl fmt::Display for Library {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut lines: Vec<String> = self.items.iter().map(|x| x.to_string()).collect();
+        if (self.items.len() as u32) < self.total {
+            lines.push(format!("...and {} more", self.total - self.items.len() as u32));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub _type: DeviceType,
+    pub is_active: bool,
+    pub volume: Option<u32>,
+}
+
+impl From<Device> for DeviceInfo {
+    fn from(dev: Device) -> Self {
+        return Self {
+            name: dev.name,
+            id: dev.id,
+            _type: dev._type,
+            is_active: dev.is_active,
+            volume: dev.volume_percent,
+        };
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Device: {}", self.name)?;
+        writeln!(f, "Id: {}", self.id.as_deref().unwrap_or("None"))?;
+        writeln!(f, "Active: {}", self.is_active)?;
+        write!(f, "Type: {:?}", self._type)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceList {
+    pub devices: Vec<DeviceInfo>,
+}
+
+impl fmt::Display for DeviceList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self.devices.iter()
+            .map(|dev| format!("{:?} {} \u{2014} {}", dev._type, dev.name, dev.id.as_deref().unwrap_or("None")))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+// any Fishify result, for front-ends that handle every command the same way
+// serialized untagged, so each command keeps the schema of its own result type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Response {
+    Action(Action),
+    Status(PlaybackStatus),
+    Queue(QueueListing),
+    Search(SearchResults),
+    Library(Library),
+    Playlist(PlaylistContents),
+    Devices(DeviceList),
+    Device(DeviceInfo),
+}
+
+impl Response {
+    pub fn show(&self) -> bool {
+        match self {
+            Self::Action(action) => action.show(),
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Action(item) => item.fmt(f),
+            Self::Status(item) => item.fmt(f),
+            Self::Queue(item) => item.fmt(f),
+            Self::Search(item) => item.fmt(f),
+            Self::Library(item) => item.fmt(f),
+            Self::Playlist(item) => item.fmt(f),
+            Self::Devices(item) => item.fmt(f),
+            Self::Device(item) => item.fmt(f),
+        }
+    }
+}
+
+// Froms
+
+impl From<Action> for Response {
+    fn from(item: Action) -> Self {
+        return Self::Action(item);
+    }
+}
+
+impl From<PlaybackStatus> for Response {
+    fn from(item: PlaybackStatus) -> Self {
+        return Self::Status(item);
+    }
+}
+
+impl From<QueueListing> for Response {
+    fn from(item: QueueListing) -> Self {
+        return Self::Queue(item);
+    }
+}
+
+impl From<SearchResults> for Response {
+    fn from(item: SearchResults) -> Self {
+        return Self::Search(item);
+    }
+}
+
+impl From<Library> for Response {
+    fn from(item: Library) -> Self {
+        return Self::Library(item);
+    }
+}
+
+impl From<PlaylistContents> for Response {
+    fn from(item: PlaylistContents) -> Self {
+        return Self::Playlist(item);
+    }
+}
+
+impl From<DeviceList> for Response {
+    fn from(item: DeviceList) -> Self {
+        return Self::Devices(item);
+    }
+}
+
+impl From<DeviceInfo> for Response {
+    fn from(item: DeviceInfo) -> Self {
+        return Self::Device(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{ json, Value, };
+
+    fn item() -> Item {
+        return Item {
+            name: "Never Gonna Give You Up".to_string(),
+            artist: Some("Rick Astley".to_string()),
+            uri: Some("spotify:track:4cOdK2wGLETKBW3PvgPWqT".to_string()),
+            duration: Some(Duration::milliseconds(213573)),
+            url: Some("https://open.spotify.com/track/4cOdK2wGLETKBW3PvgPWqT".to_string()),
+            artist_url: Some("https://open.spotify.com/artist/0gxyHStUsqpMadRV0Di1Qt".to_string()),
+            image: Some("https://i.scdn.co/image/ab67616d0000b273255e131abc1410833be95673".to_string()),
+        };
+    }
+
+    fn item_json() -> Value {
+        json!({
+            "name": "Never Gonna Give You Up",
+            "artist": "Rick Astley",
+            "uri": "spotify:track:4cOdK2wGLETKBW3PvgPWqT",
+            "duration_ms": 213573,
+            "url": "https://open.spotify.com/track/4cOdK2wGLETKBW3PvgPWqT",
+            "artist_url": "https://open.spotify.com/artist/0gxyHStUsqpMadRV0Di1Qt",
+            "image": "https://i.scdn.co/image/ab67616d0000b273255e131abc1410833be95673",
+        })
+    }
+
+    fn device() -> DeviceInfo {
+        return DeviceInfo {
+            name: "Kitchen Speaker".to_string(),
+            id: Some("5fbb3ba6aa454b5534c4ba43a8c7e8e45a63ad0e".to_string()),
+            _type: DeviceType::Speaker,
+            is_active: false,
+            volume: Some(40),
+        };
+    }
+
+    fn device_json() -> Value {
+        json!({
+            "name": "Kitchen Speaker",
+            "id": "5fbb3ba6aa454b5534c4ba43a8c7e8e45a63ad0e",
+            "type": "Speaker",
+            "is_active": false,
+            "volume": 40,
+        })
+    }
+
+    // the json the cli prints, and the same document read back from the yaml it prints
+    fn assert_schema(response: impl Into<Response>, expected: Value) {
+        let response = response.into();
+        assert_eq!(serde_json::to_value(&response).unwrap(), expected);
+        let yaml: Value = serde_yaml::from_str(&serde_yaml::to_string(&response).unwrap()).unwrap();
+        assert_eq!(yaml, expected);
+    }
+
+    #[test]
+    fn item_without_optional_fields() {
+        let episode = Item {
+            name: "Episode 1".to_string(),
+            artist: None,
+            uri: None,
+            duration: None,
+            url: None,
+            artist_url: None,
+            image: None,
+        };
+        assert_schema(Action::Queued(episode), json!({
+            "action": "queued",
+            "value": {
+                "name": "Episode 1",
+                "artist": null,
+                "uri": null,
+                "duration_ms": null,
+                "url": null,
+                "artist_url": null,
+                "image": null,
+            },
+        }));
+    }
+
+    #[test]
+    fn action_unit() {
+        assert_schema(Action::Resumed, json!({ "action": "resumed" }));
+        assert_schema(Action::Paused, json!({ "action": "paused" }));
+        assert_schema(Action::Previous, json!({ "action": "previous" }));
+        assert_schema(Action::Restarted, json!({ "action": "restarted" }));
+    }
+
+    #[test]
+    fn action_item() {
+        assert_schema(Action::Playing(item()), json!({ "action": "playing", "value": item_json() }));
+        assert_schema(Action::Queued(item()), json!({ "action": "queued", "value": item_json() }));
+        assert_schema(Action::Liked(item()), json!({ "action": "liked", "value": item_json() }));
+        assert_schema(Action::Unliked(item()), json!({ "action": "unliked", "value": item_json() }));
+    }
+
+    #[test]
+    fn action_value() {
+        assert_schema(Action::PlayingLiked(120), json!({ "action": "playing_liked", "value": 120 }));
+        assert_schema(Action::PlaylistCreated("Road trip".to_string()), json!({ "action": "playlist_created", "value": "Road trip" }));
+        assert_schema(Action::Skipped(2), json!({ "action": "skipped", "value": 2 }));
+        assert_schema(Action::Seeked(Duration::seconds(90)), json!({ "action": "seeked", "value": 90000 }));
+        assert_schema(Action::Connected("Kitchen Speaker".to_string()), json!({ "action": "connected", "value": "Kitchen Speaker" }));
+        assert_schema(Action::Volume(65), json!({ "action": "volume", "value": 65 }));
+        assert_schema(Action::Shuffle(true), json!({ "action": "shuffle", "value": true }));
+        assert_schema(Action::Repeat(RepeatState::Context), json!({ "action": "repeat", "value": "context" }));
+    }
+
+    #[test]
+    fn action_struct() {
+        let skipped = Skipped::from(&[SkipReason::LocalFile, SkipReason::Unavailable, SkipReason::LocalFile][..]);
+        assert_schema(Action::QueuedContext { item: item(), queued: 12, skipped }, json!({
+            "action": "queued_context",
+            "value": {
+                "item": item_json(),
+                "queued": 12,
+                "skipped": { "local_file": 2, "unavailable": 1 },
+            },
+        }));
+        assert_schema(Action::PlaylistAdded { playlist: "Road trip".to_string(), item: item() }, json!({
+            "action": "playlist_added",
+            "value": { "playlist": "Road trip", "item": item_json() },
+        }));
+        assert_schema(Action::PlaylistRemoved { playlist: "Road trip".to_string(), item: item() }, json!({
+            "action": "playlist_removed",
+            "value": { "playlist": "Road trip", "item": item_json() },
+        }));
+        assert_schema(Action::PlaylistMoved { playlist: "Road trip".to_string(), item: item(), position: 3 }, json!({
+            "action": "playlist_moved",
+            "value": { "playlist": "Road trip", "item": item_json(), "position": 3 },
+        }));
+        assert_schema(Action::PlaylistRenamed { playlist: "Road trip".to_string(), name: "Commute".to_string() }, json!({
+            "action": "playlist_renamed",
+            "value": { "playlist": "Road trip", "name": "Commute" },
+        }));
+    }
+
+    #[test]
+    fn status() {
+        let status = PlaybackStatus {
+            is_playing: true,
+            context: Some(PlaybackContext { _type: Type::Album, name: "Whenever You Need Somebody".to_string() }),
+            item: Some(item()),
+            progress: Some(Duration::seconds(60)),
+            volume: Some(80),
+            shuffle: false,
+            repeat: RepeatState::Off,
+        };
+        assert_schema(status, json!({
+            "is_playing": true,
+            "context": { "type": "album", "name": "Whenever You Need Somebody" },
+            "item": item_json(),
+            "progress_ms": 60000,
+            "volume": 80,
+            "shuffle": false,
+            "repeat": "off",
+        }));
+
+        let idle = PlaybackStatus {
+            is_playing: false,
+            context: None,
+            item: None,
+            progress: None,
+            volume: None,
+            shuffle: true,
+            repeat: RepeatState::Track,
+        };
+        assert_schema(idle, json!({
+            "is_playing": false,
+            "context": null,
+            "item": null,
+            "progress_ms": null,
+            "volume": null,
+            "shuffle": true,
+            "repeat": "track",
+        }));
+    }
+
+    #[test]
+    fn queue() {
+        let listing = QueueListing { currently_playing: Some(item()), queue: vec![item()] };
+        assert_schema(listing, json!({
+            "currently_playing": item_json(),
+            "queue": [item_json()],
+        }));
+    }
+
+    #[test]
+    fn search() {
+        assert_schema(SearchResults { items: vec![item(), item()] }, json!({
+            "items": [item_json(), item_json()],
+        }));
+    }
+
+    #[test]
+    fn library() {
+        assert_schema(Library { items: vec![item()], total: 3 }, json!({
+            "items": [item_json()],
+            "total": 3,
+        }));
+    }
+
+    #[test]
+    fn playlist() {
+        let playlist = PlaylistContents {
+            name: "Road trip".to_string(),
+            uri: "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M".to_string(),
+            items: vec![item()],
+            total: 1,
+        };
+        assert_schema(playlist, json!({
+            "name": "Road trip",
+            "uri": "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M",
+            "items": [item_json()],
+            "total": 1,
+        }));
+    }
+
+    #[test]
+    fn devices() {
+        assert_schema(DeviceList { devices: vec![device()] }, json!({
+            "devices": [device_json()],
+        }));
+        assert_schema(device(), device_json());
+    }
+}
@@ -0,0 +1,469 @@
+use crate::{
+    backend::SpotifyBackend,
+    device,
+    error::FishifyError,
+};
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{ Arc, Mutex, },
+    time::Instant,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use time::Duration;
+use rspotify::{
+    model::{
+        track::{ FullTrack, SavedTrack, SimplifiedTrack, },
+        album::{ FullAlbum, SavedAlbum, },
+        playlist::{ FullPlaylist, SimplifiedPlaylist, PlaylistItem, },
+        artist::FullArtist,
+        show::{ FullShow, FullEpisode, SimplifiedEpisode, Show, },
+        page::Page,
+        idtypes::{ TrackId, AlbumId, PlaylistId, ArtistId, ShowId, EpisodeId, },
+        context::{ CurrentPlaybackContext, CurrentUserQueue, },
+        device::Device,
+        enums::{
+            types::SearchType,
+            misc::RepeatState,
+        },
+        search::SearchResult,
+    },
+    prelude::{ PlayContextId, PlayableId, },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    // attempts per request, including the first
+    pub max_attempts: u32,
+    // the first wait after a server error, doubled for each attempt after
+    pub backoff: std::time::Duration,
+    // give up instead of waiting longer than this for a rate limit
+    pub max_retry_after: std::time::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        return Self {
+            max_attempts: 3,
+            backoff: std::time::Duration::from_millis(500),
+            max_retry_after: std::time::Duration::from_secs(30),
+        };
+    }
+}
+
+// At most `calls` requests every `per`, for everything sharing the backend. Requests past that
+// wait until there's room instead of being sent, so a busy front-end can't get the account rate
+// limited by spotify, and queueing an album doesn't stop halfway through.
+pub struct CallLimit {
+    calls: usize,
+    per: std::time::Duration,
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl CallLimit {
+    pub fn new(calls: usize, per: std::time::Duration) -> Self {
+        return Self {
+            // none at all would wait forever
+            calls: calls.max(1),
+            per,
+            sent: Mutex::new(VecDeque::new()),
+        };
+    }
+
+    // waits until the oldest request is `per` ago, if there are already `calls` in it
+    async fn take(&self) {
+        loop {
+            let wait = {
+                let mut sent = self.sent.lock().unwrap();
+                while sent.front().is_some_and(|x| x.elapsed() >= self.per) {
+                    sent.pop_front();
+                }
+                if sent.len() < self.calls {
+                    sent.push_back(Instant::now());
+                    return;
+                }
+                sent.front().map_or(self.per, |x| self.per.saturating_sub(x.elapsed()))
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+// Mutating requests aren't repeated after a server error, since they may have gone through,
+// which would queue a track twice or create two playlists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Idempotent,
+    Mutating,
+}
+
+// Wraps a backend to recover from errors the same way in every front-end: it connects to the
+// preferred device when there's no active one, waits out rate limits, refreshes the token
+// when spotify rejects it, and backs off on server errors. Anything else is returned as is.
+pub struct Retry<B: SpotifyBackend + ?Sized> {
+    // boxed so spotify_init's client, which could be either kind, can be wrapped too
+    inner: Box<B>,
+    policy: RetryPolicy,
+    // a device id or name to connect to, the first device if none
+    device: Option<String>,
+    // shared between backends for different accounts, since spotify limits the app as a whole
+    limit: Option<Arc<CallLimit>>,
+}
+
+impl<B: SpotifyBackend> Retry<B> {
+    pub fn new(inner: B) -> Self {
+        return Self::boxed(Box::new(inner));
+    }
+}
+
+impl<B: SpotifyBackend + ?Sized> Retry<B> {
+    pub fn boxed(inner: Box<B>) -> Self {
+        return Self {
+            inner,
+            policy: RetryPolicy::default(),
+            device: None,
+            limit: None,
+        };
+    }
+
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_device(mut self, device: Option<String>) -> Self {
+        self.device = device;
+        self
+    }
+
+    pub fn with_call_limit(mut self, limit: Option<Arc<CallLimit>>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        let devices = self.inner.devices().await?;
+        let device = match &self.device {
+            Some(target) => device::find_device(devices, target)?,
+            None => devices.into_iter().next().ok_or(FishifyError::NoDevices)?,
+        };
+        let device_id = device.id.ok_or(FishifyError::RestrictedDevice(device.name))?;
+        self.inner.transfer(&device_id).await
+    }
+
+    async fn retry<T, F, Fut>(&self, request: Request, f: F) -> Result<T>
+    where
+        F: Fn() -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T>> + Send,
+        T: Send,
+    {
+        let mut reconnected = false;
+        let mut refreshed = false;
+        let mut attempt = 1;
+
+        loop {
+            if let Some(limit) = &self.limit {
+                limit.take().await;
+            }
+            let err = match f().await {
+                Ok(result) => return Ok(result),
+                Err(err) => err,
+            };
+            if attempt >= self.policy.max_attempts {
+                return Err(err);
+            }
+            attempt += 1;
+
+            match err.downcast_ref::<FishifyError>() {
+                // only once, connecting doesn't help if the device keeps dropping
+                Some(FishifyError::NoActiveDevice) if !reconnected => {
+                    reconnected = true;
+                    if self.reconnect().await.is_err() {
+                        return Err(err);
+                    }
+                },
+                Some(FishifyError::Unauthorized) if !refreshed => {
+                    refreshed = true;
+                    if self.inner.refresh_token().await.is_err() {
+                        return Err(err);
+                    }
+                },
+                Some(FishifyError::RateLimited { retry_after }) => {
+                    let wait = std::time::Duration::from_secs(retry_after.unwrap_or(1));
+                    if wait > self.policy.max_retry_after {
+                        return Err(err);
+                    }
+                    tokio::time::sleep(wait).await;
+                },
+                Some(FishifyError::Api(status)) if (500..600).contains(status) && request == Request::Idempotent => {
+                    tokio::time::sleep(self.policy.backoff * 2u32.pow(attempt - 2)).await;
+                },
+                _ => return Err(err),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<B: SpotifyBackend + ?Sized> SpotifyBackend for Retry<B> {
+    async fn search(&self, query: &str, _type: SearchType, limit: Option<u32>, offset: Option<u32>) -> Result<SearchResult> {
+        self.retry(Request::Idempotent, || self.inner.search(query, _type, limit, offset)).await
+    }
+
+    async fn start_uris(&self, ids: Vec<PlayableId<'_>>, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.start_uris(ids.clone(), device_id)).await
+    }
+
+    async fn start_context(&self, id: PlayContextId<'_>, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.start_context(id.clone(), device_id)).await
+    }
+
+    async fn resume(&self, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.resume(device_id)).await
+    }
+
+    async fn pause(&self, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.pause(device_id)).await
+    }
+
+    async fn next(&self, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Mutating, || self.inner.next(device_id)).await
+    }
+
+    async fn previous(&self, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Mutating, || self.inner.previous(device_id)).await
+    }
+
+    async fn seek(&self, position: Duration, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.seek(position, device_id)).await
+    }
+
+    async fn add_to_queue(&self, id: PlayableId<'_>, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Mutating, || self.inner.add_to_queue(id.clone(), device_id)).await
+    }
+
+    async fn volume(&self, level: u8, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.volume(level, device_id)).await
+    }
+
+    async fn shuffle(&self, state: bool, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.shuffle(state, device_id)).await
+    }
+
+    async fn repeat(&self, state: RepeatState, device_id: Option<&str>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.repeat(state, device_id)).await
+    }
+
+    async fn devices(&self) -> Result<Vec<Device>> {
+        self.retry(Request::Idempotent, || self.inner.devices()).await
+    }
+
+    async fn transfer(&self, device_id: &str) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.transfer(device_id)).await
+    }
+
+    async fn current_playback(&self) -> Result<Option<CurrentPlaybackContext>> {
+        self.retry(Request::Idempotent, || self.inner.current_playback()).await
+    }
+
+    async fn current_queue(&self) -> Result<CurrentUserQueue> {
+        self.retry(Request::Idempotent, || self.inner.current_queue()).await
+    }
+
+    async fn track(&self, id: TrackId<'_>) -> Result<FullTrack> {
+        self.retry(Request::Idempotent, || self.inner.track(id.clone())).await
+    }
+
+    async fn album(&self, id: AlbumId<'_>) -> Result<FullAlbum> {
+        self.retry(Request::Idempotent, || self.inner.album(id.clone())).await
+    }
+
+    async fn playlist(&self, id: PlaylistId<'_>) -> Result<FullPlaylist> {
+        self.retry(Request::Idempotent, || self.inner.playlist(id.clone())).await
+    }
+
+    async fn artist(&self, id: ArtistId<'_>) -> Result<FullArtist> {
+        self.retry(Request::Idempotent, || self.inner.artist(id.clone())).await
+    }
+
+    async fn show(&self, id: ShowId<'_>) -> Result<FullShow> {
+        self.retry(Request::Idempotent, || self.inner.show(id.clone())).await
+    }
+
+    async fn episode(&self, id: EpisodeId<'_>) -> Result<FullEpisode> {
+        self.retry(Request::Idempotent, || self.inner.episode(id.clone())).await
+    }
+
+    async fn album_tracks(&self, id: AlbumId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedTrack>> {
+        self.retry(Request::Idempotent, || self.inner.album_tracks(id.clone(), limit, offset)).await
+    }
+
+    async fn show_episodes(&self, id: ShowId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedEpisode>> {
+        self.retry(Request::Idempotent, || self.inner.show_episodes(id.clone(), limit, offset)).await
+    }
+
+    async fn save_tracks(&self, ids: Vec<TrackId<'_>>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.save_tracks(ids.clone())).await
+    }
+
+    async fn unsave_tracks(&self, ids: Vec<TrackId<'_>>) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.unsave_tracks(ids.clone())).await
+    }
+
+    async fn saved_tracks(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SavedTrack>> {
+        self.retry(Request::Idempotent, || self.inner.saved_tracks(limit, offset)).await
+    }
+
+    async fn saved_albums(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SavedAlbum>> {
+        self.retry(Request::Idempotent, || self.inner.saved_albums(limit, offset)).await
+    }
+
+    async fn saved_shows(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<Show>> {
+        self.retry(Request::Idempotent, || self.inner.saved_shows(limit, offset)).await
+    }
+
+    async fn my_playlists(&self, limit: Option<u32>, offset: Option<u32>) -> Result<Page<SimplifiedPlaylist>> {
+        self.retry(Request::Idempotent, || self.inner.my_playlists(limit, offset)).await
+    }
+
+    async fn playlist_items(&self, id: PlaylistId<'_>, limit: Option<u32>, offset: Option<u32>) -> Result<Page<PlaylistItem>> {
+        self.retry(Request::Idempotent, || self.inner.playlist_items(id.clone(), limit, offset)).await
+    }
+
+    async fn playlist_create(&self, name: &str, public: bool) -> Result<FullPlaylist> {
+        self.retry(Request::Mutating, || self.inner.playlist_create(name, public)).await
+    }
+
+    async fn playlist_add(&self, id: PlaylistId<'_>, items: Vec<PlayableId<'_>>) -> Result<()> {
+        self.retry(Request::Mutating, || self.inner.playlist_add(id.clone(), items.clone())).await
+    }
+
+    async fn playlist_remove(&self, id: PlaylistId<'_>, item: PlayableId<'_>, position: u32, snapshot_id: &str) -> Result<()> {
+        self.retry(Request::Mutating, || self.inner.playlist_remove(id.clone(), item.clone(), position, snapshot_id)).await
+    }
+
+    async fn playlist_reorder(&self, id: PlaylistId<'_>, range_start: u32, insert_before: u32) -> Result<()> {
+        self.retry(Request::Mutating, || self.inner.playlist_reorder(id.clone(), range_start, insert_before)).await
+    }
+
+    async fn playlist_rename(&self, id: PlaylistId<'_>, name: &str) -> Result<()> {
+        self.retry(Request::Idempotent, || self.inner.playlist_rename(id.clone(), name)).await
+    }
+
+    async fn refresh_token(&self) -> Result<()> {
+        self.inner.refresh_token().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakePlayer;
+
+    use rspotify::model::enums::types::DeviceType;
+
+    // a player with a device that isn't active yet
+    fn retry() -> Retry<FakePlayer> {
+        let player = FakePlayer::new();
+        player.add_device("Desk", DeviceType::Computer);
+        Retry::new(player)
+    }
+
+    fn calls(retry: &Retry<FakePlayer>) -> u32 {
+        retry.inner().state().calls
+    }
+
+    fn fishify_error(err: &anyhow::Error) -> Option<&FishifyError> {
+        err.downcast_ref::<FishifyError>()
+    }
+
+    #[tokio::test]
+    async fn reconnects_without_active_device() {
+        let retry = retry();
+        retry.pause(None).await.unwrap();
+        // pause, devices, transfer, and pause again
+        assert_eq!(calls(&retry), 4);
+        assert!(retry.inner().state().active_device.is_some());
+    }
+
+    #[tokio::test]
+    async fn refreshes_token_once() {
+        let retry = retry();
+        retry.inner().fail_next(FishifyError::Unauthorized);
+        retry.devices().await.unwrap();
+        // devices, refresh_token, devices
+        assert_eq!(calls(&retry), 3);
+
+        retry.inner().fail_next(FishifyError::Unauthorized);
+        retry.inner().fail_next(FishifyError::Unauthorized);
+        let err = retry.devices().await.unwrap_err();
+        assert_eq!(fishify_error(&err), Some(&FishifyError::Unauthorized));
+        assert_eq!(calls(&retry), 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_retry_after() {
+        let retry = retry();
+        retry.inner().fail_next(FishifyError::RateLimited { retry_after: Some(2) });
+        let start = tokio::time::Instant::now();
+        retry.devices().await.unwrap();
+        assert!(start.elapsed() >= std::time::Duration::from_secs(2));
+        assert_eq!(calls(&retry), 2);
+
+        // longer than max_retry_after is left to the caller
+        retry.inner().fail_next(FishifyError::RateLimited { retry_after: Some(60) });
+        let err = retry.devices().await.unwrap_err();
+        assert_eq!(fishify_error(&err), Some(&FishifyError::RateLimited { retry_after: Some(60) }));
+        assert_eq!(calls(&retry), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_on_server_errors() {
+        let retry = retry();
+        retry.inner().fail_next(FishifyError::Api(503));
+        retry.inner().fail_next(FishifyError::Api(503));
+        let start = tokio::time::Instant::now();
+        retry.devices().await.unwrap();
+        // 500ms, then 1s
+        assert!(start.elapsed() >= std::time::Duration::from_millis(1500));
+        assert_eq!(calls(&retry), 3);
+
+        for _ in 0..3 {
+            retry.inner().fail_next(FishifyError::Api(500));
+        }
+        let err = retry.devices().await.unwrap_err();
+        assert_eq!(fishify_error(&err), Some(&FishifyError::Api(500)));
+        assert_eq!(calls(&retry), 6);
+    }
+
+    #[tokio::test]
+    async fn mutating_not_replayed() {
+        let retry = retry();
+        retry.inner().fail_next(FishifyError::Api(502));
+        let err = retry.playlist_create("Mix", false).await.unwrap_err();
+        assert_eq!(fishify_error(&err), Some(&FishifyError::Api(502)));
+        assert_eq!(calls(&retry), 1);
+        assert!(retry.inner().state().content.values().all(|x| x["type"] != "playlist"));
+    }
+
+    #[tokio::test]
+    async fn call_limit_waits() {
+        let per = std::time::Duration::from_millis(100);
+        let limit = CallLimit::new(2, per);
+        let start = Instant::now();
+        limit.take().await;
+        limit.take().await;
+        assert!(start.elapsed() < per);
+
+        // the third waits for the first to be `per` ago, instead of failing
+        limit.take().await;
+        assert!(start.elapsed() >= per);
+    }
+}
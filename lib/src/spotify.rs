@@ -1,22 +1,37 @@
-use crate::model::{ ContentInfo, ContentType, ContentId, FromSearch, };
+use crate::{
+    backend::SpotifyBackend,
+    clock::Position,
+    device,
+    error::FishifyError,
+    model::{ ContentInfo, ContentType, ContentId, FromSearch, Playables, SkipReason, },
+    pages::{ ContextPages, PageOptions, Progress, },
+    response::{
+        Item, Action, Skipped, PlaybackContext, PlaybackStatus, QueueListing, SearchResults, Library, PlaylistContents, DeviceInfo, DeviceList,
+    },
+};
 
-use time::Duration;
+use std::sync::{ Arc, OnceLock, };
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use time::Duration;
 use async_trait::async_trait;
 use rspotify::{
-    AuthCodeSpotify,
-    clients::{ OAuthClient, BaseClient, },
     model::{
         device::Device,
+        track::FullTrack,
+        idtypes::PlaylistId,
+        PlayableItem,
         enums::{
-            types::{ AdditionalType, SearchType, },
+            types::{ SearchType, },
             misc::{ RepeatState, },
         },
     },
-    prelude::{ PlayContextId, PlayableId, },
+    prelude::{ Id, PlayContextId, PlayableId, },
 };
 
+// the most saved tracks the web api returns in one page
+const SAVED_PAGE_LIMIT: u32 = 50;
+
 fn url_to_uri(url: &str) -> Option<String> {
     let base_url: &str = url.split('?').next()?;
     let mut split_url = base_url.rsplit('/');
@@ -30,275 +45,465 @@ fn url_to_uri(url: &str) -> Option<String> {
     }
 }
 
-fn duration_clock_format(duration: Duration) -> String {
-    let total_sec = duration.num_seconds();
-    let h = total_sec / 60 / 60;
-    let m = total_sec / 60 % 60;
-    let s = total_sec % 60;
-    
-    if h > 0 {
-        format!("{h}:{m:0>2}:{s:0>2}")
-    } else {
-        format!("{m}:{s:0>2}")
-    }
-}
+impl<T: SpotifyBackend + ?Sized> FishifyClient for T {}
 
-impl FishifyClient for AuthCodeSpotify {}
-
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Fishify<'a> {
-    spotify: &'a AuthCodeSpotify,
-    pub response: Vec<String>,
-    pub show: bool,
+    spotify: &'a dyn SpotifyBackend,
+    pages: PageOptions,
+    // a device id or name that commands target, the active device if none
+    device: Option<String>,
+    // the device's id, looked up by the first command that needs it
+    device_id: OnceLock<Option<String>>,
 }
 
-impl<'a> From<&'a AuthCodeSpotify> for Fishify<'a> {
-    fn from(spotify: &'a AuthCodeSpotify) -> Self {
+impl<'a, B: SpotifyBackend> From<&'a B> for Fishify<'a> {
+    fn from(spotify: &'a B) -> Self {
         return Self {
             spotify: spotify,
-            response: vec![],
-            show: true,
+            pages: PageOptions::default(),
+            device: None,
+            device_id: OnceLock::new(),
         };
     }
 }
 
 impl<'a> Fishify<'a> {
-    pub async fn play(&mut self, q: Option<String>, _type: Option<SearchType>, is_url: bool, queue: bool) -> Result<()> {
+    // the most items to queue from an album, playlist or show, None for all of them
+    pub fn with_max_items(mut self, max_items: Option<u32>) -> Self {
+        self.pages.max_items = max_items;
+        self
+    }
+
+    pub fn with_device(mut self, device: Option<String>) -> Self {
+        self.device = device;
+        self.device_id = OnceLock::new();
+        self
+    }
+
+    // the device id or name commands target, as given to with_device
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+
+    // the target device's id, so commands go to it instead of whichever device is active.
+    // looked up once, a Fishify is made per command or session, not kept around for long
+    async fn device_id(&self) -> Result<Option<String>, FishifyError> {
+        if let Some(device_id) = self.device_id.get() {
+            return Ok(device_id.clone());
+        }
+        let device_id = match &self.device {
+            Some(device) => self.spotify.device_get(Some(device.clone())).await?.id,
+            None => None,
+        };
+        Ok(self.device_id.get_or_init(|| device_id).clone())
+    }
+
+    // called while fetching and queueing large albums, playlists and shows
+    pub fn with_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.pages.progress = Arc::new(progress);
+        self
+    }
+
+    pub async fn play(&self, q: Option<String>, _type: Option<SearchType>, is_url: bool, queue: bool) -> Result<Action, FishifyError> {
+        let device_id = self.device_id().await?;
         if q.is_none() {
-            self.spotify.resume_playback(None, None).await?;
-            self.response.push("Resumed playback".to_string());
-            self.show = false;
-            return Ok(());
+            self.spotify.resume(device_id.as_deref()).await?;
+            return Ok(Action::Resumed);
         } 
         let query = q.unwrap();
 
         let search_type = _type.unwrap_or(SearchType::Track);
 
         let uri: String;
-        let id = if is_url {
-            uri = url_to_uri(&query).ok_or(anyhow!("Invalid url"))?;
-            self.spotify.play_uri(&uri, queue).await?
+        let (id, queued) = if is_url {
+            uri = url_to_uri(&query).ok_or(FishifyError::InvalidUrl(query.clone()))?;
+            self.spotify.play_uri(&uri, queue, device_id.as_deref(), &self.pages).await?
         } else {
-            self.spotify.play_query(&query, search_type, queue).await?
+            self.spotify.play_query(&query, search_type, queue, device_id.as_deref(), &self.pages).await?
         };
-        let playing = self.spotify.get_content(id).await?;
+        self.played(id, queued, queue).await
+    }
 
-        let name = playing.name();
-        let prefix: String = if queue {
-            "Queued".to_string()
+    // play or queue a spotify uri, such as one from a search result's Item
+    pub async fn play_uri(&self, uri: &str, queue: bool) -> Result<Action, FishifyError> {
+        let device_id = self.device_id().await?;
+        let (id, queued) = self.spotify.play_uri(uri, queue, device_id.as_deref(), &self.pages).await?;
+        self.played(id, queued, queue).await
+    }
+
+    // what a query, url or uri refers to, without playing or queueing it
+    pub async fn lookup(&self, q: String, _type: Option<SearchType>, is_url: bool) -> Result<Item, FishifyError> {
+        let uri = if is_url {
+            url_to_uri(&q).ok_or(FishifyError::InvalidUrl(q.clone()))?
+        } else if q.starts_with("spotify:") {
+            q
         } else {
-            "Now playing".to_string()
+            let result = self.spotify.search(&q, _type.unwrap_or(SearchType::Track), Some(1), None).await?;
+            let content = ContentType::from_search(result).next().ok_or(FishifyError::NoSearchResults)?;
+            return Ok(Item::from(&content));
         };
-        
-        if let Some(artist) = playing.artist() {
-            self.response.push(format!("{prefix} {name} by {0}", artist.name));
-        } else {
-            self.response.push(format!("{prefix} {name}"));
+        Ok(Item::from(&self.spotify.get_content(ContentId::from_uri(&uri)?).await?))
+    }
+
+    // how many items an album, playlist or show has, None for anything else
+    pub async fn context_size(&self, uri: &str) -> Result<Option<u32>, FishifyError> {
+        let mut pages = match ContextPages::new(self.spotify, ContentId::from_uri(uri)?, Some(1)) {
+            Ok(pages) => pages,
+            Err(_) => return Ok(None),
         };
+        pages.next_page().await?;
+        Ok(pages.available())
+    }
 
-        Ok(())
+    // the playable items of an album, playlist or show, up to max_items. None for anything else
+    pub async fn context_items(&self, uri: &str) -> Result<Option<Vec<Item>>, FishifyError> {
+        let mut pages = match ContextPages::new(self.spotify, ContentId::from_uri(uri)?, self.pages.max_items) {
+            Ok(pages) => pages,
+            Err(_) => return Ok(None),
+        };
+        let items = pages.collect(&self.pages).await?;
+        Ok(Some(items.iter().flatten().map(Item::from).filter(|x| x.uri.is_some()).collect()))
     }
 
-    pub async fn queue_list(&mut self) -> Result<()> {
-        let current_queue = self.spotify.current_user_queue().await?;
-        if let Some(item) = &current_queue.currently_playing {
-            let name = item.name();
-            if let Some(artist) = item.artist() {
-                self.response.push(format!("Currently playing {name} by {0}", artist.name));
-            } else {
-                self.response.push(format!("Currently playing {name}"));
-            }
+    async fn played(&self, id: ContentId<'_>, queued: Option<Playables<'static>>, queue: bool) -> Result<Action, FishifyError> {
+        let playing = Item::from(&self.spotify.get_content(id).await?);
+
+        match queued {
+            Some(playables) => Ok(Action::QueuedContext {
+                item: playing,
+                queued: playables.ids.len(),
+                skipped: Skipped::from(playables.skipped.as_slice()),
+            }),
+            None if queue => Ok(Action::Queued(playing)),
+            None => Ok(Action::Playing(playing)),
         }
+    }
 
-        for (i, item) in current_queue.queue.iter().enumerate() {
-            let name = item.name();
-            let index = i+1;
-            if let Some(artist) = item.artist() {
-                self.response.push(format!("{index:>3}. {name} \u{2014} {0}", artist.name));
-            } else {
-                self.response.push(format!("{index:>3}. {name}"));
-            }
-        }
-        Ok(())
+    pub async fn queue_list(&self) -> Result<QueueListing, FishifyError> {
+        let current_queue = self.spotify.current_queue().await?;
+
+        Ok(QueueListing {
+            currently_playing: current_queue.currently_playing.as_ref().map(Item::from),
+            queue: current_queue.queue.iter().map(Item::from).collect(),
+        })
     }
 
-    pub async fn pause(&mut self) -> Result<()> {
-        self.spotify.pause_playback(None).await?;
-        self.response.push("Paused playback".to_string());
-        self.show = false;
-        Ok(())
+    pub async fn pause(&self) -> Result<Action, FishifyError> {
+        self.spotify.pause(self.device_id().await?.as_deref()).await?;
+        Ok(Action::Paused)
     }
 
-    pub async fn skip(&mut self, count: u8) -> Result<()> {
+    pub async fn skip(&self, count: u8) -> Result<Action, FishifyError> {
+        let device_id = self.device_id().await?;
         for _ in 0..count {
-            self.spotify.next_track(None).await?;
+            self.spotify.next(device_id.as_deref()).await?;
         }
-        self.response.push(format!("Skipped {count} tracks"));
-        self.show = false;
-        Ok(())
+        Ok(Action::Skipped(count))
     }
 
-    pub async fn status(&mut self) -> Result<()> {
-        let playback = self.spotify.current_playback(None, None::<Vec<&AdditionalType>>).await?.ok_or(anyhow!("No current playback"))?;
+    pub async fn previous(&self) -> Result<Action, FishifyError> {
+        self.spotify.previous(self.device_id().await?.as_deref()).await?;
+        Ok(Action::Previous)
+    }
 
-        // This will create a message with the format:
-        //   {is_playing}
-        //   {_type} {type_name}
-        //   {name} --- {artist}
-        //   {progress} / {duration}
-        //   Volume: {volume}%
-        //   Shuffle: {shuffle}
-        //   Repeat: {repeat}
+    pub async fn seek(&self, position: Position) -> Result<Action, FishifyError> {
+        let playback = self.spotify.current_playback().await?.ok_or(FishifyError::NoActiveDevice)?;
+        let duration = playback.item.as_ref().and_then(|x| x.duration()).ok_or(FishifyError::NothingPlaying)?;
+        let progress = playback.progress.unwrap_or(Duration::zero());
 
-        if playback.is_playing {
-            self.response.push("Playing".to_string());
-        } else {
-            self.response.push("Paused".to_string());
+        let target = position.resolve(progress, duration);
+        self.spotify.seek(target, self.device_id().await?.as_deref()).await?;
+        Ok(Action::Seeked(target))
+    }
+
+    pub async fn restart(&self) -> Result<Action, FishifyError> {
+        self.spotify.seek(Duration::zero(), self.device_id().await?.as_deref()).await?;
+        Ok(Action::Restarted)
+    }
+
+    pub async fn status(&self) -> Result<PlaybackStatus, FishifyError> {
+        let playback = self.spotify.current_playback().await?.ok_or(FishifyError::NoActiveDevice)?;
+
+        let context = match playback.context {
+            Some(context) => {
+                let name = self.spotify.get_content(ContentId::from_uri(&context.uri)?).await?.name();
+                Some(PlaybackContext { _type: context._type, name })
+            },
+            None => None,
+        };
+
+        Ok(PlaybackStatus {
+            is_playing: playback.is_playing,
+            context,
+            item: playback.item.as_ref().map(Item::from),
+            progress: playback.progress,
+            volume: playback.device.volume_percent,
+            shuffle: playback.shuffle_state,
+            repeat: playback.repeat_state,
+        })
+    }
+
+    async fn current_track(&self) -> Result<FullTrack, FishifyError> {
+        let playback = self.spotify.current_playback().await?.ok_or(FishifyError::NoActiveDevice)?;
+        match playback.item {
+            Some(PlayableItem::Track(track)) => Ok(track),
+            _ => Err(FishifyError::NothingPlaying),
         }
+    }
+
+    pub async fn like(&self) -> Result<Action, FishifyError> {
+        let track = self.current_track().await?;
+        let id = track.id.clone().ok_or(FishifyError::Unplayable(SkipReason::LocalFile))?;
+        self.spotify.save_tracks(vec![id]).await?;
+        Ok(Action::Liked(Item::from(&ContentType::from(track))))
+    }
 
-        if let Some(context) = playback.context {
-            let _type = context._type;
-            let name = self.spotify.get_content(ContentId::from_uri(&context.uri).unwrap()).await?.name();
-            
-            self.response.push(format!("{_type:?}: {name}"));
+    pub async fn unlike(&self) -> Result<Action, FishifyError> {
+        let track = self.current_track().await?;
+        let id = track.id.clone().ok_or(FishifyError::Unplayable(SkipReason::LocalFile))?;
+        self.spotify.unsave_tracks(vec![id]).await?;
+        Ok(Action::Unliked(Item::from(&ContentType::from(track))))
+    }
+
+    pub async fn saved_tracks(&self, limit: Option<u32>) -> Result<Library, FishifyError> {
+        let page = self.spotify.saved_tracks(Some(limit.unwrap_or(20)), None).await?;
+        Ok(Library {
+            items: page.items.into_iter().map(|x| Item::from(&ContentType::from(x.track))).collect(),
+            total: page.total,
+        })
+    }
+
+    pub async fn saved_albums(&self, limit: Option<u32>) -> Result<Library, FishifyError> {
+        let page = self.spotify.saved_albums(Some(limit.unwrap_or(20)), None).await?;
+        Ok(Library {
+            items: page.items.into_iter().map(|x| Item::from(&ContentType::from(x.album))).collect(),
+            total: page.total,
+        })
+    }
+
+    pub async fn saved_shows(&self, limit: Option<u32>) -> Result<Library, FishifyError> {
+        let page = self.spotify.saved_shows(Some(limit.unwrap_or(20)), None).await?;
+        Ok(Library {
+            items: page.items.into_iter().map(|x| Item::from(&ContentType::from(x.show))).collect(),
+            total: page.total,
+        })
+    }
+
+    // spotify has no context uri for liked songs, so this plays them as a list of tracks,
+    // fetched a page at a time up to max_items
+    pub async fn play_liked(&self) -> Result<Action, FishifyError> {
+        let max_items = self.pages.max_items.unwrap_or(u32::MAX);
+        let mut ids: Vec<PlayableId> = vec![];
+        let mut offset = 0;
+        while offset < max_items {
+            let page = self.spotify.saved_tracks(Some((max_items - offset).min(SAVED_PAGE_LIMIT)), Some(offset)).await?;
+            let fetched = page.items.len() as u32;
+            offset += fetched;
+            ids.extend(page.items.into_iter().filter_map(|x| x.track.id).map(PlayableId::from));
+            self.pages.report(Progress::Fetched { done: offset, total: page.total.min(max_items) });
+            if fetched == 0 || offset >= page.total {
+                break;
+            }
+        }
+        if ids.is_empty() {
+            return Err(FishifyError::NoLikedSongs);
         }
 
-        if let Some(item) = playback.item {
-            let name = item.name();
+        let count = ids.len();
+        self.spotify.start_uris(ids, self.device_id().await?.as_deref()).await?;
+        Ok(Action::PlayingLiked(count))
+    }
 
-            self.response.push(
-                if let Some(artist) = item.artist() {
-                    format!("{name} \u{2014} {0}", artist.name)
-                } else {
-                    format!("{name}")
-                }
-            );
+    // a playlist by uri, url, or the name of one of the user's playlists
+    async fn find_playlist(&self, playlist: &str) -> Result<(PlaylistId<'static>, String), FishifyError> {
+        let uri = if playlist.starts_with("spotify:") {
+            Some(playlist.to_string())
+        } else {
+            url_to_uri(playlist)
+        };
+
+        if let Some(uri) = uri {
+            let id = PlaylistId::from_uri(&uri).map_err(|_| FishifyError::InvalidUri(uri.clone()))?.into_static();
+            let name = self.spotify.playlist(id.clone()).await?.name;
+            return Ok((id, name));
+        }
 
-            if let Some(progress) = playback.progress {
-                let duration = item.duration().unwrap();
-                self.response.push(format!("{} / {}", duration_clock_format(progress), duration_clock_format(duration)));
+        let mut offset = 0;
+        loop {
+            let page = self.spotify.my_playlists(Some(50), Some(offset)).await?;
+            if let Some(found) = page.items.iter().find(|x| x.name.eq_ignore_ascii_case(playlist)) {
+                return Ok((found.id.clone(), found.name.clone()));
+            }
+            offset += page.items.len() as u32;
+            if page.items.is_empty() || offset >= page.total {
+                return Err(FishifyError::PlaylistNotFound(playlist.to_string()));
             }
         }
+    }
 
-        if let Some(volume) = playback.device.volume_percent {
-            self.response.push(format!("Volume: {volume}%"));
+    // the item at a one-based position in a playlist, and how many items the playlist has
+    async fn playlist_item_at(&self, id: PlaylistId<'_>, position: u32) -> Result<(PlayableItem, u32), FishifyError> {
+        if position == 0 {
+            return Err(FishifyError::InvalidPosition(0));
         }
+        let page = self.spotify.playlist_items(id, Some(1), Some(position - 1)).await?;
+        let item = page.items.into_iter().next().ok_or(FishifyError::InvalidPosition(position))?;
+        let playable = item.track.ok_or(FishifyError::Unplayable(SkipReason::Unavailable))?;
+        Ok((playable, page.total))
+    }
 
-        let shuffle_state = if playback.shuffle_state {
-            "On"
-        } else {
-            "Off"
+    pub async fn playlist_create(&self, name: String, public: bool) -> Result<Action, FishifyError> {
+        let playlist = self.spotify.playlist_create(&name, public).await?;
+        Ok(Action::PlaylistCreated(playlist.name))
+    }
+
+    // add a search result, a url, or the current track if there's no query
+    pub async fn playlist_add(&self, playlist: &str, q: Option<String>, _type: Option<SearchType>, is_url: bool) -> Result<Action, FishifyError> {
+        let (playlist_id, name) = self.find_playlist(playlist).await?;
+
+        let content_id = match q {
+            None => {
+                let track = self.current_track().await?;
+                ContentId::from(track.id.ok_or(FishifyError::Unplayable(SkipReason::LocalFile))?)
+            },
+            Some(query) if is_url => {
+                let uri = url_to_uri(&query).ok_or(FishifyError::InvalidUrl(query.clone()))?;
+                ContentId::from_uri(&uri)?.into_static()
+            },
+            Some(query) => {
+                let result = self.spotify.search(&query, _type.unwrap_or(SearchType::Track), Some(1), None).await?;
+                ContentId::from_search(result).next().ok_or(FishifyError::NoSearchResults)?
+            },
         };
-        self.response.push(format!("Shuffle: {shuffle_state}"));
 
-        self.response.push(format!("Repeat: {:?}", playback.repeat_state));
+        let item = Item::from(&self.spotify.get_content(content_id.clone()).await?);
+        let id = content_id.playable().ok_or(FishifyError::Unplayable(SkipReason::NotPlayable))?;
+        self.spotify.playlist_add(playlist_id, vec![id]).await?;
 
-        Ok(())
+        Ok(Action::PlaylistAdded { playlist: name, item })
     }
 
-    pub async fn search(&mut self, q: String, _type: Option<SearchType>, limit: Option<u32>) -> Result<()> {
-        let result = self.spotify.search(&q, _type.unwrap_or(SearchType::Track), None, None, Some(limit.unwrap_or(10)), None).await?;
-        let results = ContentType::from_search(result);
+    // removes the item at a one-based position, leaving other occurrences of it. the snapshot is
+    // read first, so spotify doesn't remove something else if the playlist changes in the meantime
+    pub async fn playlist_remove(&self, playlist: &str, position: u32) -> Result<Action, FishifyError> {
+        let (playlist_id, name) = self.find_playlist(playlist).await?;
+        let snapshot_id = self.spotify.playlist(playlist_id.clone()).await?.snapshot_id;
+        let (playable, _) = self.playlist_item_at(playlist_id.clone(), position).await?;
+        let id = playable.id().ok_or(FishifyError::Unplayable(SkipReason::LocalFile))?;
 
-        for item in results {
-            if let Some(artist) = item.artist() {
-                self.response.push(format!("{} \u{2014} {}", item.name(), artist.name))
-            } else {
-                self.response.push(format!("{}", item.name()))
-            }
+        self.spotify.playlist_remove(playlist_id, id, position - 1, &snapshot_id).await?;
+        Ok(Action::PlaylistRemoved { playlist: name, item: Item::from(&playable) })
+    }
+
+    // move the item at one one-based position to another
+    pub async fn playlist_move(&self, playlist: &str, from: u32, to: u32) -> Result<Action, FishifyError> {
+        let (playlist_id, name) = self.find_playlist(playlist).await?;
+        let (playable, total) = self.playlist_item_at(playlist_id.clone(), from).await?;
+        if to == 0 || to > total {
+            return Err(FishifyError::InvalidPosition(to));
         }
 
-        Ok(())
+        // spotify wants the position to insert before, counted before the item is moved
+        let insert_before = if to > from { to } else { to - 1 };
+        self.spotify.playlist_reorder(playlist_id, from - 1, insert_before).await?;
+        Ok(Action::PlaylistMoved { playlist: name, item: Item::from(&playable), position: to })
     }
 
-    pub async fn device_list(&mut self) -> Result<()> {
-        let devices = self.spotify.device().await?;
+    pub async fn playlist_rename(&self, playlist: &str, new_name: String) -> Result<Action, FishifyError> {
+        let (playlist_id, name) = self.find_playlist(playlist).await?;
+        self.spotify.playlist_rename(playlist_id, &new_name).await?;
+        Ok(Action::PlaylistRenamed { playlist: name, name: new_name })
+    }
 
-        for dev in devices {
-            let name = dev.name;
-            let _type = dev._type;
-            let id = match dev.id {
-                Some(id) => id,
-                None => "None".to_string(),
-            };
-            self.response.push(format!("{_type:?} {name} \u{2014} {id}"));
-        }
+    pub async fn playlists(&self, limit: Option<u32>) -> Result<Library, FishifyError> {
+        let page = self.spotify.my_playlists(Some(limit.unwrap_or(20)), None).await?;
+        Ok(Library {
+            items: page.items.into_iter().map(|x| Item::from(&ContentType::from(x))).collect(),
+            total: page.total,
+        })
+    }
 
-        Ok(())
+    pub async fn playlist_show(&self, playlist: &str, limit: Option<u32>) -> Result<PlaylistContents, FishifyError> {
+        let (playlist_id, name) = self.find_playlist(playlist).await?;
+        let mut pages = ContextPages::new(self.spotify, ContentId::from(playlist_id.clone()), Some(limit.unwrap_or(100)))?;
+        let items = pages.collect(&self.pages).await?;
+
+        // unavailable items are kept, so positions line up with playlist_remove and playlist_move
+        let items = items.iter().map(|x| match x {
+            Some(item) => Item::from(item),
+            None => Item { name: "(unavailable)".to_string(), artist: None, uri: None, duration: None, url: None, artist_url: None, image: None },
+        }).collect();
+
+        Ok(PlaylistContents {
+            name,
+            uri: playlist_id.uri(),
+            items,
+            total: pages.available().unwrap_or(0),
+        })
     }
 
-    pub async fn device_connect(&mut self, name: Option<String>) -> Result<()> {
-        let device = self.spotify.device_get(name).await?;
-        let device_id = device.id.as_ref().ok_or(anyhow!("Missing device id"))?;
-        let device_name = device.name;
+    pub async fn search(&self, q: String, _type: Option<SearchType>, limit: Option<u32>) -> Result<SearchResults, FishifyError> {
+        let result = self.spotify.search(&q, _type.unwrap_or(SearchType::Track), Some(limit.unwrap_or(10)), None).await?;
+        let items = ContentType::from_search(result).map(|x| Item::from(&x)).collect();
 
-        self.spotify.transfer_playback(&device_id, None).await?;
-        self.response.push(format!("Connected to {device_name}"));
-        self.show = false;
+        Ok(SearchResults { items })
+    }
 
-        Ok(())
+    pub async fn device_list(&self) -> Result<DeviceList, FishifyError> {
+        let devices = self.spotify.devices().await?;
+
+        Ok(DeviceList {
+            devices: devices.into_iter().map(DeviceInfo::from).collect(),
+        })
     }
 
-    pub async fn device_status(&mut self) -> Result<()> {
-        let device = self.spotify.active_device().await?;
+    pub async fn device_connect(&self, name: Option<String>) -> Result<Action, FishifyError> {
+        let device = self.spotify.device_get(name.or(self.device.clone())).await?;
+        let device_id = device.id.as_ref().ok_or(FishifyError::RestrictedDevice(device.name.clone()))?;
 
-        match device {
-            Some(dev) => {
-                let name = dev.name;
-                let id = match dev.id {
-                    Some(id) => id,
-                    None => "None".to_string(),
-                };
-                let is_active = dev.is_active.to_string();
-                let _type = dev._type;
-                self.response.push(format!("Device: {name}"));
-                self.response.push(format!("Id: {id}"));
-                self.response.push(format!("Active: {is_active}"));
-                self.response.push(format!("Type: {_type:?}"));
-            },
-            None => return Err(anyhow!("No active device")),
-        }
+        self.spotify.transfer(device_id).await?;
 
-        Ok(())
+        Ok(Action::Connected(device.name))
     }
 
-    pub async fn set_volume(&mut self, level: u8) -> Result<()> {
-        self.spotify.volume(level, None).await?;
-        self.response.push(format!("Set volume to {level}"));
-        self.show = false;
-        Ok(())
+    pub async fn device_status(&self) -> Result<DeviceInfo, FishifyError> {
+        let device = self.spotify.active_device().await?.ok_or(FishifyError::NoActiveDevice)?;
+
+        Ok(DeviceInfo::from(device))
     }
 
-    pub async fn set_shuffle(&mut self, state: bool) -> Result<()> {
-        self.spotify.shuffle(state, None).await?;
-        self.response.push(format!("Set shuffle to {state}"));
-        self.show = false;
-        Ok(())
+    pub async fn set_volume(&self, level: u8) -> Result<Action, FishifyError> {
+        self.spotify.volume(level, self.device_id().await?.as_deref()).await?;
+        Ok(Action::Volume(level))
     }
 
-    pub async fn set_repeat(&mut self, state: RepeatState) -> Result<()> {
-        self.spotify.repeat(state, None).await?;
-        self.response.push(format!("Set repeat to {state:?}"));
-        self.show = false;
-        Ok(())
+    pub async fn set_shuffle(&self, state: bool) -> Result<Action, FishifyError> {
+        self.spotify.shuffle(state, self.device_id().await?.as_deref()).await?;
+        Ok(Action::Shuffle(state))
+    }
+
+    pub async fn set_repeat(&self, state: RepeatState) -> Result<Action, FishifyError> {
+        self.spotify.repeat(state, self.device_id().await?.as_deref()).await?;
+        Ok(Action::Repeat(state))
     }
 }
 
 #[async_trait]
-trait FishifyClient: OAuthClient + BaseClient {
+trait FishifyClient: SpotifyBackend {
     async fn device_get(&self, name: Option<String>) -> Result<Device> {
-        let devices: Vec<Device> = self.device().await?;
+        let devices: Vec<Device> = self.devices().await?;
 
         let device = match name {
-            Some(target) => devices.into_iter().find(|device| device.name == target).ok_or(anyhow!("Device not found"))?,
-            None => devices.into_iter().next().ok_or(anyhow!("No devices found"))?,
+            Some(target) => device::find_device(devices, &target)?,
+            None => devices.into_iter().next().ok_or(FishifyError::NoDevices)?,
         };
 
         Ok(device)
     }
 
     async fn active_device(&self) -> Result<Option<Device>> {
-        let playback_option = self.current_playback(None, None::<Vec<&AdditionalType>>).await?;
+        let playback_option = self.current_playback().await?;
 
         match playback_option {
             Some(playback) => Ok(Some(playback.device)),
@@ -306,61 +511,387 @@ trait FishifyClient: OAuthClient + BaseClient {
         }
     }
 
-    async fn play_query(&self, query: &str, _type: SearchType, queue: bool) -> Result<ContentId> {
-        let result = self.search(query, _type, None, None, Some(1), None).await?;
-        let id = ContentId::from_search(result).next().ok_or(anyhow!("No search result"))?;
-        self.play_id(id.clone(), queue).await?;
-        Ok(id)
+    async fn play_query(&self, query: &str, _type: SearchType, queue: bool, device_id: Option<&str>, pages: &PageOptions) -> Result<(ContentId, Option<Playables<'static>>)> {
+        let result = self.search(query, _type, Some(1), None).await?;
+        let id = ContentId::from_search(result).next().ok_or(FishifyError::NoSearchResults)?;
+        let queued = self.play_id(id.clone(), queue, device_id, pages).await?;
+        Ok((id, queued))
     }
 
-    async fn play_uri(&'async_trait self, uri: &'async_trait str, queue: bool) -> Result<ContentId> {
+    async fn play_uri(&'async_trait self, uri: &'async_trait str, queue: bool, device_id: Option<&str>, pages: &PageOptions) -> Result<(ContentId, Option<Playables<'static>>)> {
         let id = ContentId::from_uri(uri)?;
-        self.play_id(id.clone(), queue).await?;
-        Ok(id)
+        let queued = self.play_id(id.clone(), queue, device_id, pages).await?;
+        Ok((id, queued))
     }
 
-    async fn play_id(&self, content_id: ContentId<'async_trait>, queue: bool) -> Result<()> {
+    // what was queued and skipped, when queueing an album, playlist or show
+    async fn play_id(&self, content_id: ContentId<'async_trait>, queue: bool, device_id: Option<&str>, pages: &PageOptions) -> Result<Option<Playables<'static>>> {
         if !queue {
             match content_id {
-                ContentId::Track(id) => self.start_uris_playback([PlayableId::from(id)], None, None, None).await?,
-                ContentId::Episode(id) => self.start_uris_playback([PlayableId::from(id)], None, None, None).await?,
-                ContentId::Album(id) => self.start_context_playback(PlayContextId::from(id), None, None, None).await?,
-                ContentId::Playlist(id) => self.start_context_playback(PlayContextId::from(id), None, None, None).await?,
-                ContentId::Artist(id) => self.start_context_playback(PlayContextId::from(id), None, None, None).await?,
-                ContentId::Show(id) => self.start_context_playback(PlayContextId::from(id), None, None, None).await?,
+                ContentId::Track(id) => self.start_uris(vec![PlayableId::from(id)], device_id).await?,
+                ContentId::Episode(id) => self.start_uris(vec![PlayableId::from(id)], device_id).await?,
+                ContentId::Album(id) => self.start_context(PlayContextId::from(id), device_id).await?,
+                ContentId::Playlist(id) => self.start_context(PlayContextId::from(id), device_id).await?,
+                ContentId::Artist(id) => self.start_context(PlayContextId::from(id), device_id).await?,
+                ContentId::Show(id) => self.start_context(PlayContextId::from(id), device_id).await?,
             }
+            Ok(None)
         } else {
             match content_id {
-                ContentId::Track(id) => self.add_item_to_queue(PlayableId::from(id), None).await?,
-                ContentId::Episode(id) => self.add_item_to_queue(PlayableId::from(id), None).await?,
-                ContentId::Album(id) => self.queue_context_id(PlayContextId::from(id)).await?,
-                ContentId::Playlist(id) => self.queue_context_id(PlayContextId::from(id)).await?,
-                ContentId::Artist(id) => self.queue_context_id(PlayContextId::from(id)).await?,
-                ContentId::Show(id) => self.queue_context_id(PlayContextId::from(id)).await?,
+                ContentId::Track(id) => self.add_to_queue(PlayableId::from(id), device_id).await?,
+                ContentId::Episode(id) => self.add_to_queue(PlayableId::from(id), device_id).await?,
+                ContentId::Album(id) => return Ok(Some(self.queue_context_id(PlayContextId::from(id), device_id, pages).await?)),
+                ContentId::Playlist(id) => return Ok(Some(self.queue_context_id(PlayContextId::from(id), device_id, pages).await?)),
+                ContentId::Artist(id) => return Ok(Some(self.queue_context_id(PlayContextId::from(id), device_id, pages).await?)),
+                ContentId::Show(id) => return Ok(Some(self.queue_context_id(PlayContextId::from(id), device_id, pages).await?)),
             }
+            Ok(None)
         }
-        Ok(())
     }
 
-    async fn queue_context_id(&self, context_id: PlayContextId<'async_trait>) -> Result<()> {
+    // local files and unavailable items are skipped instead of failing the whole context
+    async fn queue_context_id(&self, context_id: PlayContextId<'async_trait>, device_id: Option<&str>, pages: &PageOptions) -> Result<Playables<'static>> {
         let content_id = ContentId::from(context_id);
-        let context = self.get_content(content_id).await?;
-        let ids: Vec<PlayableId> = context.ids().ok_or(anyhow!("Failed to queue"))?;
-        for id in ids {
-            self.add_item_to_queue(id, None).await?;
+        let items = ContextPages::new(self, content_id, pages.max_items)?.collect(pages).await?;
+        let playables = Playables::from_items(items);
+
+        let total = playables.ids.len() as u32;
+        for (i, id) in playables.ids.iter().enumerate() {
+            self.add_to_queue(id.clone(), device_id).await?;
+            pages.report(Progress::Queued { done: i as u32 + 1, total });
         }
-        Ok(())
+        Ok(playables)
     }
 
     async fn get_content(&self, content_id: ContentId<'async_trait>) -> Result<ContentType> {
         match content_id {
             ContentId::Track(id) => Ok(ContentType::from(self.track(id).await?)),
-            ContentId::Episode(id) => Ok(ContentType::from(self.get_an_episode(id, None).await?)),
+            ContentId::Episode(id) => Ok(ContentType::from(self.episode(id).await?)),
             ContentId::Album(id) => Ok(ContentType::from(self.album(id).await?)),
-            ContentId::Playlist(id) => Ok(ContentType::from(self.playlist(id, None, None).await?)),
+            ContentId::Playlist(id) => Ok(ContentType::from(self.playlist(id).await?)),
             ContentId::Artist(id) => Ok(ContentType::from(self.artist(id).await?)),
-            ContentId::Show(id) => Ok(ContentType::from(self.get_a_show(id, None).await?)),
+            ContentId::Show(id) => Ok(ContentType::from(self.show(id).await?)),
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakePlayer;
+
+    use rspotify::model::enums::types::{ DeviceType, Type, };
+
+    // a player with an active device, an album of three tracks, and a single of the first one
+    struct Fixture {
+        player: FakePlayer,
+        tracks: Vec<String>,
+        album: String,
+        single: String,
+    }
+
+    fn fixture() -> Fixture {
+        let player = FakePlayer::new();
+        let device = player.add_device("Desk", DeviceType::Computer);
+        player.state().active_device = Some(device);
+
+        let artist = player.add_artist("Rick Astley");
+        let tracks: Vec<String> = [("Never Gonna Give You Up", 213573), ("Whenever You Need Somebody", 234000), ("Together Forever", 205000)]
+            .iter()
+            .map(|(name, duration_ms)| player.add_track(name, &artist, *duration_ms))
+            .collect();
+        let uris: Vec<&str> = tracks.iter().map(|x| x.as_str()).collect();
+        let album = player.add_album("Whenever You Need Somebody", &artist, &uris);
+        let single = player.add_album("Never Gonna Give You Up", &artist, &uris[..1]);
+
+        return Fixture {
+            player,
+            tracks,
+            album,
+            single,
+        };
+    }
+
+    fn current(player: &FakePlayer) -> Option<String> {
+        player.state().current.clone()
+    }
+
+    #[tokio::test]
+    async fn play() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+
+        let action = fishify.play(Some("never gonna".to_string()), None, false, false).await.unwrap();
+        assert!(matches!(action, Action::Playing(item) if item.name == "Never Gonna Give You Up"));
+        assert_eq!(current(&f.player), Some(f.tracks[0].clone()));
+        assert!(f.player.state().is_playing);
+
+        fishify.pause().await.unwrap();
+        assert!(!f.player.state().is_playing);
+        assert_eq!(fishify.play(None, None, false, false).await.unwrap(), Action::Resumed);
+        assert!(f.player.state().is_playing);
+    }
+
+    #[tokio::test]
+    async fn play_without_results() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+
+        let err = fishify.play(Some("darude".to_string()), None, false, false).await.unwrap_err();
+        assert_eq!(err, FishifyError::NoSearchResults);
+    }
+
+    #[tokio::test]
+    async fn skip_and_previous() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+
+        let action = fishify.play_uri(&f.album, false).await.unwrap();
+        assert!(matches!(action, Action::Playing(item) if item.name == "Whenever You Need Somebody"));
+        assert_eq!(current(&f.player), Some(f.tracks[0].clone()));
+
+        assert_eq!(fishify.skip(2).await.unwrap(), Action::Skipped(2));
+        assert_eq!(current(&f.player), Some(f.tracks[2].clone()));
+
+        assert_eq!(fishify.previous().await.unwrap(), Action::Previous);
+        assert_eq!(current(&f.player), Some(f.tracks[1].clone()));
+
+        // past the end of the album
+        fishify.skip(2).await.unwrap();
+        assert_eq!(current(&f.player), None);
+        assert!(!f.player.state().is_playing);
+    }
+
+    #[tokio::test]
+    async fn skip_with_repeat() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+
+        // a context of one track loops
+        fishify.play_uri(&f.single, false).await.unwrap();
+        fishify.set_repeat(RepeatState::Context).await.unwrap();
+        fishify.skip(3).await.unwrap();
+        assert_eq!(current(&f.player), Some(f.tracks[0].clone()));
+        assert!(f.player.state().is_playing);
+
+        // and a longer one comes back around
+        fishify.play_uri(&f.album, false).await.unwrap();
+        fishify.skip(4).await.unwrap();
+        assert_eq!(current(&f.player), Some(f.tracks[1].clone()));
+
+        fishify.set_repeat(RepeatState::Track).await.unwrap();
+        fishify.skip(2).await.unwrap();
+        assert_eq!(current(&f.player), Some(f.tracks[1].clone()));
+    }
+
+    #[tokio::test]
+    async fn queue_list() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+
+        fishify.play_uri(&f.album, false).await.unwrap();
+        let action = fishify.play(Some("together".to_string()), None, false, true).await.unwrap();
+        assert!(matches!(action, Action::Queued(item) if item.name == "Together Forever"));
+
+        let listing = fishify.queue_list().await.unwrap();
+        assert_eq!(listing.currently_playing.map(|x| x.name), Some("Never Gonna Give You Up".to_string()));
+        let names: Vec<String> = listing.queue.into_iter().map(|x| x.name).collect();
+        // what was queued goes before the rest of the album
+        assert_eq!(names, ["Together Forever", "Whenever You Need Somebody", "Together Forever"]);
+    }
+
+    #[tokio::test]
+    async fn context_items() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player).with_max_items(Some(2));
+
+        let items = fishify.context_items(&f.album).await.unwrap().unwrap();
+        let uris: Vec<Option<String>> = items.into_iter().map(|x| x.uri).collect();
+        assert_eq!(uris, [Some(f.tracks[0].clone()), Some(f.tracks[1].clone())]);
+        assert_eq!(fishify.context_items(&f.tracks[0]).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn play_liked() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+
+        let err = fishify.play_liked().await.unwrap_err();
+        assert_eq!(err, FishifyError::NoLikedSongs);
+
+        // more than a page of them
+        let artist = f.player.add_artist("Various Artists");
+        for i in 0..60 {
+            let track = f.player.add_track(&format!("Track {i}"), &artist, 180000);
+            f.player.save(&track);
+        }
+        assert_eq!(fishify.play_liked().await.unwrap(), Action::PlayingLiked(60));
+        assert_eq!(f.player.state().upcoming.len(), 59);
+
+        let capped = fishify.clone().with_max_items(Some(10));
+        assert_eq!(capped.play_liked().await.unwrap(), Action::PlayingLiked(10));
+    }
+
+    #[tokio::test]
+    async fn playlist_remove() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+        let uri = f.player.add_playlist("Road trip", &[f.tracks[0].as_str(), f.tracks[1].as_str(), f.tracks[0].as_str()]);
+
+        let action = fishify.playlist_remove("road trip", 3).await.unwrap();
+        assert!(matches!(action, Action::PlaylistRemoved { playlist, item } if playlist == "Road trip" && item.name == "Never Gonna Give You Up"));
+        // only the occurrence at that position
+        let contents = fishify.playlist_show(&uri, None).await.unwrap();
+        let names: Vec<String> = contents.items.into_iter().map(|x| x.name).collect();
+        assert_eq!(names, ["Never Gonna Give You Up", "Whenever You Need Somebody"]);
+
+        let err = fishify.playlist_remove("road trip", 3).await.unwrap_err();
+        assert_eq!(err, FishifyError::InvalidPosition(3));
+    }
+
+    fn playlist_names(player: &FakePlayer, uri: &str) -> Vec<String> {
+        let state = player.state();
+        state.content[uri]["tracks"]["items"].as_array().unwrap().iter()
+            .map(|x| x["track"]["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn playlist_move() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+        let uri = f.player.add_playlist("Road trip", &[f.tracks[0].as_str(), f.tracks[1].as_str(), f.tracks[2].as_str()]);
+
+        let action = fishify.playlist_move("road trip", 1, 3).await.unwrap();
+        assert!(matches!(action, Action::PlaylistMoved { playlist, item, position: 3 } if playlist == "Road trip" && item.name == "Never Gonna Give You Up"));
+        assert_eq!(playlist_names(&f.player, &uri), ["Whenever You Need Somebody", "Together Forever", "Never Gonna Give You Up"]);
+
+        fishify.playlist_move("road trip", 3, 2).await.unwrap();
+        assert_eq!(playlist_names(&f.player, &uri), ["Whenever You Need Somebody", "Never Gonna Give You Up", "Together Forever"]);
+        // to where it already is
+        fishify.playlist_move("road trip", 2, 2).await.unwrap();
+        assert_eq!(playlist_names(&f.player, &uri), ["Whenever You Need Somebody", "Never Gonna Give You Up", "Together Forever"]);
+    }
+
+    #[tokio::test]
+    async fn playlist_move_out_of_range() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+        let uri = f.player.add_playlist("Road trip", &[f.tracks[0].as_str(), f.tracks[1].as_str()]);
+
+        assert_eq!(fishify.playlist_move("road trip", 0, 1).await.unwrap_err(), FishifyError::InvalidPosition(0));
+        assert_eq!(fishify.playlist_move("road trip", 3, 1).await.unwrap_err(), FishifyError::InvalidPosition(3));
+        assert_eq!(fishify.playlist_move("road trip", 1, 0).await.unwrap_err(), FishifyError::InvalidPosition(0));
+        assert_eq!(fishify.playlist_move("road trip", 1, 3).await.unwrap_err(), FishifyError::InvalidPosition(3));
+        assert_eq!(playlist_names(&f.player, &uri), ["Never Gonna Give You Up", "Whenever You Need Somebody"]);
+    }
+
+    #[tokio::test]
+    async fn playlist_remove_out_of_range() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+        let uri = f.player.add_playlist("Road trip", &[f.tracks[0].as_str()]);
+
+        assert_eq!(fishify.playlist_remove("road trip", 0).await.unwrap_err(), FishifyError::InvalidPosition(0));
+        assert_eq!(fishify.playlist_remove("road trip", 2).await.unwrap_err(), FishifyError::InvalidPosition(2));
+        assert_eq!(playlist_names(&f.player, &uri), ["Never Gonna Give You Up"]);
+    }
+
+    #[tokio::test]
+    async fn playlist_add() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+        let uri = f.player.add_playlist("Road trip", &[]);
+
+        let action = fishify.playlist_add("road trip", Some("together".to_string()), None, false).await.unwrap();
+        assert!(matches!(action, Action::PlaylistAdded { playlist, item } if playlist == "Road trip" && item.name == "Together Forever"));
+        assert_eq!(playlist_names(&f.player, &uri), ["Together Forever"]);
+
+        // the current track without a query
+        fishify.play_uri(&f.album, false).await.unwrap();
+        fishify.skip(1).await.unwrap();
+        let action = fishify.playlist_add("road trip", None, None, false).await.unwrap();
+        assert!(matches!(action, Action::PlaylistAdded { item, .. } if item.name == "Whenever You Need Somebody"));
+        assert_eq!(playlist_names(&f.player, &uri), ["Together Forever", "Whenever You Need Somebody"]);
+
+        let err = fishify.playlist_add("road trip", Some("darude".to_string()), None, false).await.unwrap_err();
+        assert_eq!(err, FishifyError::NoSearchResults);
+        let err = fishify.playlist_add("beach", Some("together".to_string()), None, false).await.unwrap_err();
+        assert_eq!(err, FishifyError::PlaylistNotFound("beach".to_string()));
+    }
+
+    #[tokio::test]
+    async fn playlist_add_nothing_playing() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+        let uri = f.player.add_playlist("Road trip", &[]);
+
+        let err = fishify.playlist_add("road trip", None, None, false).await.unwrap_err();
+        assert_eq!(err, FishifyError::NothingPlaying);
+        assert!(playlist_names(&f.player, &uri).is_empty());
+    }
+
+    #[tokio::test]
+    async fn playlist_rename() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+        let uri = f.player.add_playlist("Road trip", &[]);
+
+        let action = fishify.playlist_rename("road trip", "Beach".to_string()).await.unwrap();
+        assert_eq!(action, Action::PlaylistRenamed { playlist: "Road trip".to_string(), name: "Beach".to_string() });
+        assert_eq!(f.player.state().content[&uri]["name"], "Beach");
+        assert!(fishify.playlist_move("road trip", 1, 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn device_looked_up_once() {
+        let f = fixture();
+        let kitchen = f.player.add_device("Kitchen Speaker", DeviceType::Speaker);
+        let fishify = Fishify::from(&f.player).with_device(Some("kitchen".to_string()));
+
+        fishify.play_uri(&f.album, false).await.unwrap();
+        assert_eq!(f.player.state().active_device, Some(kitchen.clone()));
+
+        // renamed after the lookup, commands still go to the device it found
+        for device in f.player.state().devices.iter_mut().filter(|x| x.id.as_ref() == Some(&kitchen)) {
+            device.name = "Garage".to_string();
+        }
+        fishify.skip(1).await.unwrap();
+        assert_eq!(current(&f.player), Some(f.tracks[1].clone()));
+
+        // changing the device looks it up again
+        let err = fishify.with_device(Some("kitchen".to_string())).pause().await.unwrap_err();
+        assert_eq!(err, FishifyError::DeviceNotFound("kitchen".to_string()));
+    }
+
+    #[tokio::test]
+    async fn status() {
+        let f = fixture();
+        let fishify = Fishify::from(&f.player);
+
+        fishify.play_uri(&f.album, false).await.unwrap();
+        fishify.set_volume(40).await.unwrap();
+        fishify.set_shuffle(true).await.unwrap();
+
+        let status = fishify.status().await.unwrap();
+        assert!(status.is_playing);
+        assert_eq!(status.context, Some(PlaybackContext { _type: Type::Album, name: "Whenever You Need Somebody".to_string() }));
+        let item = status.item.unwrap();
+        assert_eq!(item.name, "Never Gonna Give You Up");
+        assert_eq!(item.artist.as_deref(), Some("Rick Astley"));
+        assert_eq!(item.duration, Some(Duration::milliseconds(213573)));
+        assert_eq!(status.volume, Some(40));
+        assert!(status.shuffle);
+        assert_eq!(status.repeat, RepeatState::Off);
+    }
+
+    #[tokio::test]
+    async fn status_without_device() {
+        let player = FakePlayer::new();
+        let fishify = Fishify::from(&player);
+
+        let err = fishify.status().await.unwrap_err();
+        assert_eq!(err, FishifyError::NoActiveDevice);
+    }
+}